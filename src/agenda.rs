@@ -0,0 +1,56 @@
+use crate::task::Task;
+use chrono::prelude::*;
+
+/// The headings the agenda sorts due tasks under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AgendaGroup {
+    Overdue,
+    Today,
+    Tomorrow,
+    /// After tomorrow, up to and including Sunday.
+    ThisWeek,
+    Later,
+}
+
+impl AgendaGroup {
+    pub fn title(self) -> &'static str {
+        match self {
+            AgendaGroup::Overdue => "Overdue",
+            AgendaGroup::Today => "Today",
+            AgendaGroup::Tomorrow => "Tomorrow",
+            AgendaGroup::ThisWeek => "This Week",
+            AgendaGroup::Later => "Later",
+        }
+    }
+
+    pub fn of(due: NaiveDate, today: NaiveDate) -> AgendaGroup {
+        let end_of_week = today + chrono::Duration::days(6 - i64::from(today.weekday().num_days_from_monday()));
+        match (due - today).num_days() {
+            days if days < 0 => AgendaGroup::Overdue,
+            0 => AgendaGroup::Today,
+            1 => AgendaGroup::Tomorrow,
+            _ if due <= end_of_week => AgendaGroup::ThisWeek,
+            _ => AgendaGroup::Later,
+        }
+    }
+}
+
+/// A task's due date, if it is set and is a date rather than free text.
+pub fn due_date(task: &Task) -> Option<NaiveDate> {
+    task.due_day()
+}
+
+/// The same day as the calendar widget counts them.
+pub fn calendar_date(date: NaiveDate) -> Option<time::Date> {
+    let month = time::Month::try_from(u8::try_from(date.month()).ok()?).ok()?;
+    time::Date::from_calendar_date(date.year(), month, u8::try_from(date.day()).ok()?).ok()
+}
+
+/// The day drawn at `column` (0 for Sunday) of `week` in the month grid
+/// of `month`'s month, counting weeks from the one holding the 1st.
+pub fn grid_date(month: NaiveDate, week: u32, column: u32) -> Option<NaiveDate> {
+    let first = month.with_day(1)?;
+    let start = first - chrono::Duration::days(first.weekday().num_days_from_sunday().into());
+    let date = start + chrono::Duration::days(i64::from(week * 7 + column));
+    (column < 7).then_some(date)
+}
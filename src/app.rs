@@ -1,13 +1,139 @@
-use crate::task::{load_tasks, save_tasks, Task, Priority};
+use crate::agenda;
+use crate::board::{self, Grouping};
+use crate::command::ExportFormat;
+use crate::config::{load_config, Config};
+use crate::document::{export_markdown, parse_document, render_document, task_line, ParsedTask};
+use crate::keymap::{Action, Key, KeyContext, Keymap};
+use crate::pomodoro::{Phase, Pomodoro};
+use crate::report::{self, format_duration};
+use crate::sort::SortMode;
+use crate::theme::{load_themes, Theme};
+use crate::task::{
+    load_archive, load_tasks, max_blocker_id, max_id, save_archive, save_tasks, Priority, Project, SavedView,
+    Settings, Status, Task, TaskFile,
+};
 use chrono::prelude::*;
 use chrono_english::{parse_date_string, Dialect};
+use ratatui::layout::{Position, Rect};
+use ratatui::style::Color;
 use ratatui::widgets::ListState;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+const UNDO_LIMIT: usize = 100;
 
 pub enum AppMode {
     Normal,
     Insert,
     DateInput,
     Search,
+    /// Multi-select: rows are marked with space and actions apply to every
+    /// marked task.
+    Select,
+    TagInput,
+    /// Editing the selected task's notes in the multi-line popup.
+    Notes,
+    /// Waiting for the user to confirm a destructive action.
+    Confirm(PendingAction),
+    /// Listing every key, starting with the context it was opened from.
+    Help(KeyContext),
+    /// Typing a `:` command.
+    Command,
+    /// Picking an action or saved view, opened from the given context.
+    Picker(KeyContext),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PendingAction {
+    /// Delete the task under the cursor, along with its subtasks.
+    Delete,
+    /// Delete every marked task.
+    BulkDelete,
+    /// Archive every completed task.
+    ArchiveAll,
+    /// Permanently delete everything in the archive.
+    Purge,
+    /// Delete the project being shown, moving its tasks up to its parent.
+    DeleteProject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    Tasks,
+    /// Top-level tasks as cards in columns, see `board::columns`.
+    Board,
+    /// Tasks that can be started now: open, not blocked or waiting, and
+    /// without open subtasks, from every level of the tree.
+    Next,
+    /// Open tasks with a due date, grouped by when they are due, next to a
+    /// month calendar.
+    Agenda,
+    /// Charts of completions, open tasks and streaks, see `stats::stats`.
+    Stats,
+    Archive,
+}
+
+/// Focus mode hides completed tasks, the footer and borders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    Off,
+    On,
+    /// Shows only the top-level task with this ID and its subtasks.
+    Single(usize),
+}
+
+/// Which project's tasks the list shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectFilter {
+    All,
+    /// Tasks without a project.
+    Inbox,
+    /// A project and the projects nested under it.
+    Project(usize),
+}
+
+/// One line of the task list: the index path from `App.tasks` (or
+/// `App.archive` when `archived` is set) down to the task shown on that line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub path: Vec<usize>,
+    pub archived: bool,
+}
+
+/// Where things were drawn in the last frame, for mapping mouse clicks.
+#[derive(Debug, Default)]
+pub struct ScreenAreas {
+    /// The task list's rows, inside its border.
+    pub list: Rect,
+    /// Footer key hints, clickable to run their action.
+    pub footer_keys: Vec<(Rect, Action)>,
+    pub popup: Option<Rect>,
+    /// Each board column's card area, with the rows of the cards from the
+    /// top of that area down.
+    pub board: Vec<(Rect, Vec<usize>)>,
+    /// Sidebar entries, clickable to switch project.
+    pub sidebar: Vec<(Rect, ProjectFilter)>,
+    /// The agenda's row on each line of `list` from the top down, with
+    /// `None` for the group headings.
+    pub agenda: Vec<Option<usize>>,
+    /// Each day drawn in the agenda's calendar.
+    pub calendar: Vec<(Rect, NaiveDate)>,
+}
+
+/// Task state saved before each change so it can be undone.
+struct Snapshot {
+    tasks: Vec<Task>,
+    archive: Vec<Task>,
+    projects: Vec<Project>,
+}
+
+impl Row {
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
 }
 
 pub struct App {
@@ -19,19 +145,107 @@ pub struct App {
     pub search_input: String,
     pub margin: u16,
     pub adding_subtask: bool,
+    pub sort_mode: SortMode,
+    pub show_details: bool,
+    pub archive: Vec<Task>,
+    pub view: View,
+    /// Whether searching the task list also matches archived tasks.
+    pub search_archive: bool,
+    pub config: Config,
+    /// One-off feedback shown in the footer until the next key press.
+    pub status_message: Option<String>,
+    pub tag_input: String,
+    /// IDs of the tasks marked in select mode.
+    pub marked: HashSet<usize>,
+    undo_stack: Vec<Snapshot>,
+    pub theme: Theme,
+    /// Built-in themes followed by those loaded from the themes directory.
+    pub themes: Vec<Theme>,
+    pub focus: Focus,
+    pub notes_input: String,
+    pub areas: ScreenAreas,
+    pub keymap: Keymap,
+    /// Keys typed so far of a multi-key sequence such as `gg`.
+    pub pending_keys: Vec<Key>,
+    pub help_scroll: u16,
+    pub command_input: String,
+    pub views: Vec<SavedView>,
+    pub picker_input: String,
+    /// Index of the highlighted entry among the picker's matches.
+    pub picker_selected: usize,
+    pub projects: Vec<Project>,
+    pub project_filter: ProjectFilter,
+    pub show_sidebar: bool,
+    pub board_grouping: Grouping,
+    /// The board column the cursor is in. A task with several tags shows in
+    /// more than one column, so the selected row alone is not enough.
+    pub board_column: usize,
+    /// The day the agenda is narrowed to, if one is picked.
+    pub agenda_day: Option<NaiveDate>,
+    /// The first of the month the agenda's calendar shows.
+    pub agenda_month: NaiveDate,
+    pub pomodoro: Option<Pomodoro>,
 }
 
 impl App {
     pub fn new() -> App {
+        let file = load_tasks("tasks.json").unwrap_or_default();
+        let mut errors = Vec::new();
+        let config = load_config("config.toml").unwrap_or_else(|e| {
+            errors.push(format!("config.toml: {}", e));
+            Config::default()
+        });
+        let (user_themes, theme_errors) = load_themes("themes");
+        errors.extend(theme_errors);
+        let archive = load_archive("archive.json").unwrap_or_default();
+        let mut app = App::from_parts(file, archive, config, user_themes, errors);
+        if let Some(days) = app.config.archive_after_days {
+            let cutoff = Local::now() - chrono::Duration::days(days.into());
+            app.archive_where(|task| task.completed_at.is_some_and(|at| at < cutoff));
+        }
+        app
+    }
+
+    /// An app holding just `tasks` and `projects`, with the default config.
+    #[cfg(test)]
+    pub fn with_tasks(tasks: Vec<Task>, projects: Vec<Project>) -> App {
+        let file = TaskFile {
+            settings: Settings::default(),
+            projects,
+            tasks,
+        };
+        App::from_parts(file, Vec::new(), Config::default(), Vec::new(), Vec::new())
+    }
+
+    /// Builds the app from what `new` loads, with `errors` so far shown as
+    /// the first status message.
+    fn from_parts(
+        file: TaskFile,
+        archive: Vec<Task>,
+        mut config: Config,
+        user_themes: Vec<Theme>,
+        mut errors: Vec<String>,
+    ) -> App {
+        let (keymap, key_errors) = Keymap::from_config(&config.keys);
+        errors.extend(key_errors);
+        errors.extend(config.pomodoro.clamp());
+        let themes: Vec<Theme> = Theme::built_in()
+            .into_iter()
+            .chain(user_themes)
+            .map(|theme| theme.downsample(config.color_mode))
+            .collect();
+        let theme_name = file.settings.theme.clone().or(config.theme.clone());
+        let theme = theme_name
+            .and_then(|name| find_theme(&themes, &name))
+            .or_else(|| find_theme(&themes, "mocha"))
+            .cloned()
+            .unwrap();
         let mut state = ListState::default();
-        if !load_tasks("tasks.json")
-            .unwrap_or_else(|_| Vec::new())
-            .is_empty()
-        {
+        if !file.tasks.is_empty() {
             state.select(Some(0));
         }
         App {
-            tasks: load_tasks("tasks.json").unwrap_or_else(|_| Vec::new()),
+            tasks: file.tasks,
             state,
             mode: AppMode::Normal,
             input: String::new(),
@@ -39,6 +253,36 @@ impl App {
             search_input: String::new(),
             margin: 1,
             adding_subtask: false,
+            sort_mode: file.settings.sort_mode,
+            views: file.settings.views,
+            show_details: false,
+            archive,
+            view: View::Tasks,
+            search_archive: false,
+            config,
+            status_message: (!errors.is_empty()).then(|| errors.join("; ")),
+            tag_input: String::new(),
+            marked: HashSet::new(),
+            undo_stack: Vec::new(),
+            theme,
+            themes,
+            focus: Focus::Off,
+            notes_input: String::new(),
+            areas: ScreenAreas::default(),
+            keymap,
+            pending_keys: Vec::new(),
+            help_scroll: 0,
+            command_input: String::new(),
+            picker_input: String::new(),
+            picker_selected: 0,
+            show_sidebar: !file.projects.is_empty(),
+            projects: file.projects,
+            project_filter: ProjectFilter::All,
+            board_grouping: file.settings.board,
+            board_column: 0,
+            agenda_day: None,
+            agenda_month: Local::now().date_naive().with_day(1).unwrap(),
+            pomodoro: None,
         }
     }
 
@@ -51,194 +295,2245 @@ impl App {
     }
 
 
+    /// Which keybindings apply in the current mode and view.
+    pub fn key_context(&self) -> KeyContext {
+        match self.mode {
+            AppMode::Normal if self.view == View::Archive => KeyContext::Archive,
+            AppMode::Normal if self.view == View::Board => KeyContext::Board,
+            AppMode::Normal if self.view == View::Agenda => KeyContext::Agenda,
+            AppMode::Normal if self.view == View::Stats => KeyContext::Stats,
+            AppMode::Normal => KeyContext::Tasks,
+            AppMode::Select => KeyContext::Select,
+            AppMode::Insert | AppMode::DateInput | AppMode::TagInput => KeyContext::Input,
+            AppMode::Search => KeyContext::Search,
+            AppMode::Notes => KeyContext::Notes,
+            AppMode::Confirm(_) => KeyContext::Confirm,
+            AppMode::Help(_) => KeyContext::Help,
+            AppMode::Command => KeyContext::Command,
+            AppMode::Picker(_) => KeyContext::Picker,
+        }
+    }
+
     pub fn next(&mut self) {
-        let displayed_tasks = self.get_displayed_tasks();
-        if displayed_tasks.is_empty() {
+        if self.view == View::Board {
+            return self.step_in_column(true);
+        }
+        let rows = self.visible_rows();
+        if rows.is_empty() {
             return;
         }
-        let i = self.state.selected().map_or(0, |i| (i + 1) % displayed_tasks.len());
+        let i = self.state.selected().map_or(0, |i| (i + 1) % rows.len());
         self.state.select(Some(i));
     }
 
     pub fn previous(&mut self) {
-        let displayed_tasks = self.get_displayed_tasks();
-        if displayed_tasks.is_empty() {
+        if self.view == View::Board {
+            return self.step_in_column(false);
+        }
+        let rows = self.visible_rows();
+        if rows.is_empty() {
             return;
         }
-        let i = self.state.selected().map_or(0, |i| (i + displayed_tasks.len() - 1) % displayed_tasks.len());
+        let i = self.state.selected().map_or(0, |i| (i + rows.len() - 1) % rows.len());
         self.state.select(Some(i));
     }
 
+    pub fn select_first(&mut self) {
+        if !self.visible_rows().is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    pub fn select_last(&mut self) {
+        let len = self.visible_rows().len();
+        if len > 0 {
+            self.state.select(Some(len - 1));
+        }
+    }
+
+    pub fn set_theme(&mut self, name: &str) {
+        match find_theme(&self.themes, name) {
+            Some(theme) => {
+                self.theme = theme.clone();
+                self.status_message = Some(format!("Theme: {}", self.theme.name));
+            }
+            None => self.status_message = Some(format!("Unknown theme: {}", name)),
+        }
+    }
+
+    pub fn cycle_theme(&mut self) {
+        let i = self
+            .themes
+            .iter()
+            .position(|theme| theme.name == self.theme.name)
+            .map_or(0, |i| (i + 1) % self.themes.len());
+        self.theme = self.themes[i].clone();
+        self.status_message = Some(format!("Theme: {}", self.theme.name));
+    }
+
+    pub fn toggle_focus(&mut self) {
+        let focus = match self.focus {
+            Focus::Off => Focus::On,
+            _ => Focus::Off,
+        };
+        self.set_focus(focus);
+    }
+
+    /// Focuses on the top-level task the cursor is in, or leaves focus mode
+    /// if already focused on one.
+    pub fn toggle_single_focus(&mut self) {
+        let focus = match (self.focus, self.selected_path()) {
+            (Focus::Single(_), _) | (_, None) => Focus::Off,
+            (_, Some(path)) => Focus::Single(self.tasks[path[0]].id),
+        };
+        self.set_focus(focus);
+    }
+
+    fn set_focus(&mut self, focus: Focus) {
+        let selected = self.selected_path();
+        self.focus = focus;
+        match selected {
+            Some(path) if self.visible_rows().iter().any(|row| !row.archived && row.path == path) => {
+                self.select_path(&path)
+            }
+            _ => self.clamp_selection(),
+        }
+    }
+
+    pub fn toggle_details(&mut self) {
+        self.show_details = !self.show_details;
+    }
+
+    pub fn cycle_sort(&mut self) {
+        self.set_sort(self.sort_mode.next());
+    }
+
+    pub fn set_sort(&mut self, sort_mode: SortMode) {
+        let selected = self.selected_path();
+        self.sort_mode = sort_mode;
+        // Keep the cursor on the same task now that the rows have moved
+        if let Some(path) = selected {
+            self.select_path(&path);
+        }
+    }
+
+    /// Completes the target tasks, or reopens them if they are all done
+    /// already.
     pub fn toggle_completed(&mut self) {
-        if let Some(selected_index) = self.state.selected() {
-            let displayed_tasks = self.get_displayed_tasks();
-            if let Some(selected_task) = displayed_tasks.get(selected_index) {
-                // Find the task in the main tasks vector by ID
-                if let Some(main_task) = self.tasks.iter_mut().find(|t| t.id == selected_task.id) {
-                    main_task.completed = !main_task.completed;
+        let paths = self.target_paths();
+        if paths.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        let blocked = self.blocked_ids();
+        let complete = paths
+            .iter()
+            .any(|path| self.task(path).is_some_and(|task| !task.is_closed()));
+        for path in &paths {
+            if let Some(task) = self.task_mut(path) {
+                task.set_completed(complete);
+            }
+        }
+        self.report_unblocked(&blocked);
+    }
+
+    /// Every status in workflow order: the built-in open states, then the
+    /// custom ones from config.toml, then done and cancelled.
+    pub fn statuses(&self) -> Vec<Status> {
+        let mut statuses = vec![Status::Todo, Status::Doing, Status::Waiting];
+        for state in self.config.states.iter() {
+            let status = Status::from(state.name.clone());
+            if matches!(status, Status::Custom(_)) && !statuses.contains(&status) {
+                statuses.push(status);
+            }
+        }
+        statuses.extend([Status::Done, Status::Cancelled]);
+        statuses
+    }
+
+    /// A built-in or configured status by name or alias.
+    pub fn find_status(&self, name: &str) -> Option<Status> {
+        self.statuses()
+            .into_iter()
+            .find(|status| status.name().eq_ignore_ascii_case(name))
+            .or_else(|| Status::built_in(name))
+    }
+
+    pub fn status_symbol(&self, status: &Status) -> String {
+        self.config
+            .states
+            .iter()
+            .find(|state| matches!(status, Status::Custom(name) if *name == state.name))
+            .map_or(status.symbol().to_string(), |state| state.symbol.clone())
+    }
+
+    /// Sets the status of the target tasks by name. `reason` says what a
+    /// waiting task is waiting on and is ignored for other states.
+    pub fn set_status(&mut self, name: &str, reason: &str) {
+        let Some(status) = self.find_status(name) else {
+            self.status_message = Some(format!("Unknown status: {}", name));
+            return;
+        };
+        let paths = self.target_paths();
+        if paths.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        let blocked = self.blocked_ids();
+        for path in &paths {
+            if let Some(task) = self.task_mut(path) {
+                task.set_status(status.clone());
+                if status == Status::Waiting && !reason.is_empty() {
+                    task.waiting_on = Some(reason.to_string());
+                }
+            }
+        }
+        self.report_unblocked(&blocked);
+    }
+
+    /// The task whose timer is running, if any.
+    pub fn timing_task(&self) -> Option<&Task> {
+        self.tasks.iter().flat_map(Task::with_subtasks).find(|task| task.is_timing())
+    }
+
+    /// Starts the timer on the selected task, stopping whichever timer was
+    /// running, or stops it if it is the one running.
+    pub fn toggle_timer(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        let Some(task) = self.task(&path) else {
+            return;
+        };
+        if task.is_closed() {
+            self.status_message = Some("Reopen the task to time it".to_string());
+            return;
+        }
+        let id = task.id;
+        let running = self.timing_task().map(|task| task.id);
+        self.checkpoint();
+        if let Some(task) = running.and_then(|running| find_id_mut(&mut self.tasks, running)) {
+            task.stop_timer();
+            if task.id == id {
+                self.status_message = Some(format!("Stopped timer: {} in all", format_duration(task.time_spent())));
+                return;
+            }
+        }
+        if let Some(task) = self.task_mut(&path) {
+            task.start_timer();
+            self.status_message = Some(format!("Timing: {}", task.description));
+        }
+    }
+
+    /// Starts a pomodoro on the selected task, or stops the one running on
+    /// it. Starting one replaces any pomodoro on another task.
+    pub fn toggle_pomodoro(&mut self) {
+        let Some((id, description)) = self.editable_task().map(|task| (task.id, task.description.clone())) else {
+            return;
+        };
+        if self.pomodoro.as_ref().is_some_and(|pomodoro| pomodoro.task_id == id) {
+            self.pomodoro = None;
+            self.status_message = Some("Pomodoro stopped".to_string());
+            return;
+        }
+        self.pomodoro = Some(Pomodoro::start(id, &self.config.pomodoro));
+        self.status_message = Some(format!(
+            "Pomodoro: {} min on {}",
+            self.config.pomodoro.work_minutes, description
+        ));
+    }
+
+    /// Moves the pomodoro on when its phase is over, counting finished work
+    /// phases on the task. Returns whether a phase ended. The pomodoro stops
+    /// quietly once its task is gone.
+    pub fn tick(&mut self) -> bool {
+        let Some(pomodoro) = self.pomodoro.as_mut() else {
+            return false;
+        };
+        let id = pomodoro.task_id;
+        if find_id(&self.tasks, id).is_none() {
+            self.pomodoro = None;
+            return false;
+        }
+        let Some(ended) = pomodoro.advance(&self.config.pomodoro) else {
+            return false;
+        };
+        let phase = pomodoro.phase;
+        let task = find_id_mut(&mut self.tasks, id).unwrap();
+        if ended == Phase::Work {
+            task.pomodoros += 1;
+        }
+        let minutes = phase.minutes(&self.config.pomodoro);
+        self.status_message = Some(match phase {
+            Phase::Work => format!("Break over: {} min on {}", minutes, task.description),
+            _ => format!(
+                "Pomodoro {} done on {}: take a {} min {}",
+                task.pomodoros,
+                task.description,
+                minutes,
+                phase.label()
+            ),
+        });
+        true
+    }
+
+    /// Shows time tracked by tag or project, see `report::parse_args`.
+    pub fn show_report(&mut self, args: &str) {
+        self.status_message = Some(match report::parse_args(args) {
+            Ok((grouping, from, to)) => {
+                let report = report::report(self, grouping, from, to);
+                let rows: Vec<String> = report
+                    .rows
+                    .iter()
+                    .map(|(name, time)| format!("{} {}", name, format_duration(*time)))
+                    .collect();
+                format!(
+                    "{} to {}: {}; total {}",
+                    report.from,
+                    report.to,
+                    if rows.is_empty() { "nothing tracked".to_string() } else { rows.join(", ") },
+                    format_duration(report.total)
+                )
+            }
+            Err(e) => e,
+        });
+    }
+
+    /// The task with this ID, wherever it is in the tree or the archive.
+    pub fn find_id(&self, id: usize) -> Option<&Task> {
+        find_id(&self.tasks, id).or_else(|| find_id(&self.archive, id))
+    }
+
+    /// The open tasks `task` is waiting for. Blockers that are closed,
+    /// archived or deleted no longer block.
+    pub fn blockers(&self, task: &Task) -> Vec<&Task> {
+        task.blocked_by
+            .iter()
+            .filter_map(|&id| find_id(&self.tasks, id))
+            .filter(|blocker| !blocker.is_closed())
+            .collect()
+    }
+
+    pub fn is_blocked(&self, task: &Task) -> bool {
+        !self.blockers(task).is_empty()
+    }
+
+    /// The tasks that list `id` as a blocker.
+    pub fn dependents(&self, id: usize) -> Vec<&Task> {
+        let mut paths = Vec::new();
+        collect_paths(&self.tasks, &mut Vec::new(), &|task| task.blocked_by.contains(&id), &mut paths);
+        paths.iter().filter_map(|path| self.task(path)).collect()
+    }
+
+    fn blocked_ids(&self) -> HashSet<usize> {
+        let mut paths = Vec::new();
+        collect_paths(&self.tasks, &mut Vec::new(), &|task| self.is_blocked(task), &mut paths);
+        paths.iter().filter_map(|path| self.task(path)).map(|task| task.id).collect()
+    }
+
+    /// Names the tasks that were blocked before a change and no longer are.
+    fn report_unblocked(&mut self, blocked: &HashSet<usize>) {
+        let still_blocked = self.blocked_ids();
+        let unblocked: Vec<String> = blocked
+            .difference(&still_blocked)
+            .filter_map(|&id| find_id(&self.tasks, id))
+            .map(|task| task.description.clone())
+            .collect();
+        if !unblocked.is_empty() {
+            self.status_message = Some(format!("Unblocked: {}", unblocked.join(", ")));
+        }
+    }
+
+    /// Whether making `task` wait for `blocker` would close a loop, because
+    /// `blocker` already waits for `task`, directly or through others.
+    fn would_cycle(&self, task: usize, blocker: usize) -> bool {
+        let mut stack = vec![blocker];
+        let mut seen = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if id == task {
+                return true;
+            }
+            if seen.insert(id) {
+                stack.extend(self.find_id(id).map_or(&[][..], |t| &t.blocked_by[..]));
+            }
+        }
+        false
+    }
+
+    /// Links `task` to wait for `blocker`, unless that is not a valid link.
+    fn add_dependency(&mut self, task: usize, blocker: usize) -> Result<(), String> {
+        if self.find_id(blocker).is_none() {
+            return Err(format!("No task with ID {}", blocker));
+        }
+        if task == blocker {
+            return Err("A task cannot wait for itself".to_string());
+        }
+        if self.would_cycle(task, blocker) {
+            return Err(format!("That would make a cycle: #{} already waits for #{}", blocker, task));
+        }
+        let task = find_id_mut(&mut self.tasks, task).ok_or(format!("No task with ID {}", task))?;
+        if !task.blocked_by.contains(&blocker) {
+            task.blocked_by.push(blocker);
+            task.touch();
+        }
+        Ok(())
+    }
+
+    /// Applies a dependency edit such as `3 +4 -5` to the target tasks:
+    /// plain or `+` IDs become blockers, `-` IDs stop being ones, and `none`
+    /// removes them all. Links that would make a cycle are refused.
+    pub fn edit_dependencies(&mut self, edit: &str) {
+        let ids: Vec<usize> = self
+            .target_paths()
+            .iter()
+            .filter_map(|path| self.task(path))
+            .map(|task| task.id)
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        let blocked = self.blocked_ids();
+        let mut errors = Vec::new();
+        for &id in &ids {
+            for word in edit.split_whitespace() {
+                let (remove, number) = match word.strip_prefix('-') {
+                    Some(number) => (true, number),
+                    None => (false, word.strip_prefix('+').unwrap_or(word)),
+                };
+                let task = find_id_mut(&mut self.tasks, id).unwrap();
+                if word == "none" {
+                    task.blocked_by.clear();
+                    task.touch();
+                    continue;
+                }
+                let Ok(blocker) = number.trim_start_matches('#').parse::<usize>() else {
+                    errors.push(format!("Not a task ID: {}", word));
+                    continue;
+                };
+                if remove {
+                    task.blocked_by.retain(|&b| b != blocker);
+                    task.touch();
+                } else if let Err(e) = self.add_dependency(id, blocker) {
+                    errors.push(e);
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        errors.retain(|e| seen.insert(e.clone()));
+        self.report_unblocked(&blocked);
+        if !errors.is_empty() {
+            self.status_message = Some(errors.join("; "));
+        }
+    }
+
+    /// Makes the target tasks block each of the tasks with the given IDs.
+    pub fn add_blocks(&mut self, edit: &str) {
+        let ids: Vec<usize> = self
+            .target_paths()
+            .iter()
+            .filter_map(|path| self.task(path))
+            .map(|task| task.id)
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        let mut errors = Vec::new();
+        for word in edit.split_whitespace() {
+            let Ok(dependent) = word.trim_start_matches('#').parse::<usize>() else {
+                errors.push(format!("Not a task ID: {}", word));
+                continue;
+            };
+            for &id in &ids {
+                if let Err(e) = self.add_dependency(dependent, id) {
+                    errors.push(e);
                 }
             }
         }
+        let mut seen = HashSet::new();
+        errors.retain(|e| seen.insert(e.clone()));
+        if !errors.is_empty() {
+            self.status_message = Some(errors.join("; "));
+        }
     }
 
     pub fn cycle_priority(&mut self) {
-        if let Some(selected_index) = self.state.selected() {
-            let displayed_tasks = self.get_displayed_tasks();
-            if let Some(selected_task) = displayed_tasks.get(selected_index) {
-                // Find the task in the main tasks vector by ID
-                if let Some(main_task) = self.tasks.iter_mut().find(|t| t.id == selected_task.id) {
-                    main_task.priority = match main_task.priority {
-                        Priority::Low => Priority::Medium,
-                        Priority::Medium => Priority::High,
-                        Priority::High => Priority::Low,
-                    };
+        let paths = self.target_paths();
+        if paths.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        for path in &paths {
+            if let Some(task) = self.task_mut(path) {
+                task.priority = task.priority.next();
+                task.touch();
+            }
+        }
+    }
+
+    pub fn edit_tags(&mut self) {
+        let edit: String = self.tag_input.drain(..).collect();
+        let paths = self.target_paths();
+        if !paths.is_empty() {
+            self.checkpoint();
+            for path in &paths {
+                if let Some(task) = self.task_mut(path) {
+                    task.edit_tags(&edit);
                 }
             }
         }
+        self.leave_input();
     }
 
-    pub fn save(&self) {
-        save_tasks("tasks.json", &self.tasks).unwrap_or_else(|_| {});
+    /// Opens the notes popup on the selected task.
+    pub fn start_notes(&mut self) {
+        if let Some(notes) = self.editable_task().map(|t| t.notes.clone()) {
+            self.notes_input = notes;
+            self.mode = AppMode::Notes;
+        }
     }
 
-    pub fn add_task(&mut self) {
-        if self.adding_subtask {
-            self.add_sub_task();
-            self.adding_subtask = false;
+    pub fn save_notes(&mut self) {
+        let notes = std::mem::take(&mut self.notes_input);
+        self.set_notes(notes);
+        self.leave_input();
+    }
+
+    pub fn set_notes(&mut self, notes: String) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        self.checkpoint();
+        if let Some(task) = self.task_mut(&path) {
+            // Editors usually add a trailing newline
+            task.notes = notes.trim_end().to_string();
+            task.touch();
+        }
+    }
+
+    /// Returns from an input popup to select mode if tasks are still marked,
+    /// or to normal mode otherwise.
+    pub fn leave_input(&mut self) {
+        self.mode = if self.marked.is_empty() {
+            AppMode::Normal
         } else {
-            let new_id = self.tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
-            let due_date = parse_date_string(&self.input, Local::now(), Dialect::Us)
-                .ok()
-                .map(|date| date.format("%Y-%m-%d").to_string());
-            let tags = self
-                .input
-                .split_whitespace()
-                .filter(|word| word.starts_with('#'))
-                .map(|word| word.to_string())
-                .collect();
-
-            let new_task = Task {
-                id: new_id,
-                description: self.input.drain(..).collect(),
-                completed: false,
-                priority: Priority::Medium,
-                due_date,
-                sub_tasks: Box::new(Vec::new()),
-                tags,
-            };
-            self.tasks.push(new_task);
+            AppMode::Select
+        };
+    }
+
+    /// The text field being typed into, if the mode has one.
+    pub fn input_field_mut(&mut self) -> Option<&mut String> {
+        match self.mode {
+            AppMode::Insert => Some(&mut self.input),
+            AppMode::DateInput => Some(&mut self.date_input),
+            AppMode::Search => Some(&mut self.search_input),
+            AppMode::TagInput => Some(&mut self.tag_input),
+            AppMode::Notes => Some(&mut self.notes_input),
+            AppMode::Command => Some(&mut self.command_input),
+            AppMode::Picker(_) => Some(&mut self.picker_input),
+            _ => None,
         }
-        self.mode = AppMode::Normal;
     }
 
-    pub fn add_sub_task(&mut self) {
-        if let Some(selected_index) = self.state.selected() {
-            let displayed_tasks = self.get_displayed_tasks();
-            if let Some(selected_task) = displayed_tasks.get(selected_index) {
-                // Find the task in the main tasks vector by ID
-                if let Some(main_task) = self.tasks.iter_mut().find(|t| t.id == selected_task.id) {
-                    let new_id = main_task.sub_tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
-                    let due_date = parse_date_string(&self.input, Local::now(), Dialect::Us)
-                        .ok()
-                        .map(|date| date.format("%Y-%m-%d").to_string());
-                    let tags = self
-                        .input
-                        .split_whitespace()
-                        .filter(|word| word.starts_with('#'))
-                        .map(|word| word.to_string())
-                        .collect();
-
-                    let new_task = Task {
-                        id: new_id,
-                        description: self.input.drain(..).collect(),
-                        completed: false,
-                        priority: Priority::Medium,
-                        due_date,
-                        sub_tasks: Box::new(Vec::new()),
-                        tags,
-                    };
-                    main_task.sub_tasks.push(new_task);
-                }
+    /// Inserts pasted text into the field being edited. Line breaks are kept
+    /// in notes and become spaces everywhere else.
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let multi_line = matches!(self.mode, AppMode::Notes);
+        if let Some(field) = self.input_field_mut() {
+            if multi_line {
+                field.push_str(&text);
+            } else {
+                field.push_str(&text.lines().collect::<Vec<_>>().join(" "));
             }
         }
-        self.mode = AppMode::Normal;
     }
 
-    pub fn set_due_date(&mut self) {
-        if let Some(selected_index) = self.state.selected() {
-            let displayed_tasks = self.get_displayed_tasks();
-            if let Some(selected_task) = displayed_tasks.get(selected_index) {
-                // Find the task in the main tasks vector by ID
-                if let Some(main_task) = self.tasks.iter_mut().find(|t| t.id == selected_task.id) {
-                    main_task.due_date = Some(self.date_input.drain(..).collect());
-                }
+    pub fn toggle_mark(&mut self) {
+        if let Some(id) = self.editable_task().map(|t| t.id) {
+            if !self.marked.remove(&id) {
+                self.marked.insert(id);
             }
+            self.next();
         }
+    }
+
+    /// Marks every task matching the current filter.
+    pub fn mark_all(&mut self) {
+        let ids: Vec<usize> = self
+            .visible_rows()
+            .iter()
+            .filter(|row| !row.archived)
+            .filter_map(|row| self.task(&row.path).map(|task| task.id))
+            .collect();
+        self.marked.extend(ids);
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
         self.mode = AppMode::Normal;
     }
 
-    pub fn delete_task(&mut self) {
-        if let Some(selected_index) = self.state.selected() {
-            let displayed_tasks = self.get_displayed_tasks();
-            if let Some(selected_task) = displayed_tasks.get(selected_index) {
-                // Find and remove the task in the main tasks vector by ID
-                if let Some(main_index) = self.tasks.iter().position(|t| t.id == selected_task.id) {
-                    self.tasks.remove(main_index);
-                }
-                
-                // Update selection
-                let new_displayed_tasks = self.get_displayed_tasks();
-                if !new_displayed_tasks.is_empty() {
-                    self.state.select(Some(selected_index.min(new_displayed_tasks.len() - 1)));
-                } else {
-                    self.state.select(None);
+    /// The tasks an action applies to: every marked task, or the selected
+    /// task when nothing is marked.
+    fn target_paths(&self) -> Vec<Vec<usize>> {
+        if self.marked.is_empty() {
+            return self.selected_path().into_iter().collect();
+        }
+        let mut paths = Vec::new();
+        collect_paths(&self.tasks, &mut Vec::new(), &|task| self.marked.contains(&task.id), &mut paths);
+        paths
+    }
+
+    fn checkpoint(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(Snapshot {
+            tasks: self.tasks.clone(),
+            archive: self.archive.clone(),
+            projects: self.projects.clone(),
+        });
+    }
+
+    pub fn undo(&mut self) {
+        match self.undo_stack.pop() {
+            Some(snapshot) => {
+                self.tasks = snapshot.tasks;
+                self.archive = snapshot.archive;
+                self.projects = snapshot.projects;
+                if let ProjectFilter::Project(id) = self.project_filter {
+                    if self.project(id).is_none() {
+                        self.project_filter = ProjectFilter::All;
+                    }
                 }
+                self.status_message = Some("Undone".to_string());
+                self.clamp_selection();
             }
+            None => self.status_message = Some("Nothing to undo".to_string()),
         }
     }
 
-    pub fn filter_tasks(&self) -> Vec<Task> {
-        if self.search_input.is_empty() {
-            return self.tasks.clone();
+    pub fn save(&self) {
+        let settings = Settings {
+            sort_mode: self.sort_mode,
+            theme: Some(self.theme.name.clone()),
+            views: self.views.clone(),
+            board: self.board_grouping,
+        };
+        save_tasks("tasks.json", &self.tasks, &self.projects, &settings).unwrap_or(());
+        save_archive("archive.json", &self.archive).unwrap_or(());
+    }
+
+    pub fn toggle_view(&mut self) {
+        self.set_view(match self.view {
+            View::Tasks => View::Board,
+            View::Board => View::Next,
+            View::Next => View::Agenda,
+            View::Agenda => View::Stats,
+            View::Stats => View::Archive,
+            View::Archive => View::Tasks,
+        });
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        self.view = view;
+        let rows = self.visible_rows();
+        self.state.select(if rows.is_empty() { None } else { Some(0) });
+        self.board_column = 0;
+        self.sync_board();
+    }
+
+    /// Moves the board's cursor into the column holding the selected card,
+    /// unless the current column holds it too.
+    pub fn sync_board(&mut self) {
+        let Some(selected) = self.state.selected() else {
+            return;
+        };
+        let columns = board::columns(self);
+        if columns.get(self.board_column).is_some_and(|c| c.rows.contains(&selected)) {
+            return;
+        }
+        if let Some(i) = columns.iter().position(|c| c.rows.contains(&selected)) {
+            self.board_column = i;
         }
+    }
 
-        let search_lower = self.search_input.to_lowercase();
-        self.tasks
+    fn step_in_column(&mut self, forward: bool) {
+        let columns = board::columns(self);
+        let Some(rows) = columns.get(self.board_column).map(|c| &c.rows).filter(|r| !r.is_empty()) else {
+            return;
+        };
+        let i = match self.state.selected().and_then(|s| rows.iter().position(|&r| r == s)) {
+            Some(i) if forward => (i + 1) % rows.len(),
+            Some(i) => (i + rows.len() - 1) % rows.len(),
+            None => 0,
+        };
+        self.state.select(Some(rows[i]));
+    }
+
+    /// Moves the board's cursor to a neighbouring column, keeping roughly
+    /// the same height.
+    pub fn select_column(&mut self, offset: isize) {
+        let columns = board::columns(self);
+        let Some(target) = self.board_column.checked_add_signed(offset).filter(|&c| c < columns.len()) else {
+            return;
+        };
+        let height = columns
+            .get(self.board_column)
+            .and_then(|c| c.rows.iter().position(|&r| Some(r) == self.state.selected()))
+            .unwrap_or(0);
+        let rows = &columns[target].rows;
+        self.state.select(rows.get(height).or(rows.last()).copied());
+        self.board_column = target;
+    }
+
+    /// Moves the selected card to a neighbouring column, changing its
+    /// status, priority or tags to match.
+    pub fn move_card(&mut self, offset: isize) {
+        let columns = board::columns(self);
+        let Some(target) = self.board_column.checked_add_signed(offset).filter(|&c| c < columns.len()) else {
+            return;
+        };
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        self.checkpoint();
+        let blocked = self.blocked_ids();
+        let from = &columns[self.board_column].group;
+        let to = &columns[target].group;
+        let task = self.task_mut(&path).unwrap();
+        to.move_task(from, task);
+        let id = task.id;
+        // Tag columns come and go with the tags themselves
+        let columns = board::columns(self);
+        let rows = self.visible_rows();
+        let row = rows.iter().position(|row| self.row_task(row).is_some_and(|task| task.id == id));
+        self.state.select(row);
+        self.board_column = columns
             .iter()
-            .filter(|task| {
-                // Filter by description (case-insensitive)
-                task.description.to_lowercase().contains(&search_lower)
-                    // Filter by tags (case-insensitive)
-                    || task.tags.iter().any(|tag| tag.to_lowercase().contains(&search_lower))
-                    // Filter by priority
-                    || match search_lower.as_str() {
-                        "high" | "h" => matches!(task.priority, Priority::High),
-                        "medium" | "med" | "m" => matches!(task.priority, Priority::Medium),
-                        "low" | "l" => matches!(task.priority, Priority::Low),
-                        _ => false,
-                    }
-                    // Filter by completion status
-                    || match search_lower.as_str() {
-                        "completed" | "done" | "finished" => task.completed,
-                        "incomplete" | "pending" | "todo" => !task.completed,
-                        _ => false,
-                    }
-                    // Filter by due date (if it exists)
-                    || task.due_date.as_ref().map_or(false, |date| date.contains(&search_lower))
-                    // Filter by subtasks content
-                    || task.sub_tasks.iter().any(|subtask| {
-                        subtask.description.to_lowercase().contains(&search_lower)
-                            || subtask.tags.iter().any(|tag| tag.to_lowercase().contains(&search_lower))
-                    })
-            })
-            .cloned()
-            .collect()
+            .position(|c| c.group == *to)
+            .unwrap_or(self.board_column);
+        self.report_unblocked(&blocked);
     }
 
-    pub fn get_displayed_tasks(&self) -> Vec<Task> {
-        match self.mode {
-            AppMode::Search if !self.search_input.is_empty() => self.filter_tasks(),
-            _ => self.tasks.clone(),
+    /// The board column drawn at `position`, if any.
+    pub fn column_at(&self, position: Position) -> Option<usize> {
+        self.areas.board.iter().position(|(area, _)| area.contains(position))
+    }
+
+    pub fn set_board_grouping(&mut self, grouping: Grouping) {
+        self.board_grouping = grouping;
+        self.board_column = 0;
+        self.sync_board();
+    }
+
+    pub fn cycle_board_grouping(&mut self) {
+        self.set_board_grouping(self.board_grouping.next());
+    }
+
+    /// Runs `action`, first asking for confirmation if the config says to.
+    pub fn request(&mut self, action: PendingAction) {
+        let confirm = &self.config.confirm;
+        let needs_confirmation = match action {
+            PendingAction::Delete => confirm.delete,
+            PendingAction::BulkDelete => confirm.bulk_delete,
+            PendingAction::ArchiveAll => confirm.archive_all,
+            PendingAction::Purge => confirm.purge,
+            PendingAction::DeleteProject => confirm.delete_project,
+        };
+        if needs_confirmation {
+            self.mode = AppMode::Confirm(action);
+        } else {
+            self.perform(action);
+        }
+    }
+
+    pub fn confirm(&mut self) {
+        if let AppMode::Confirm(action) = self.mode {
+            self.perform(action);
+            self.leave_input();
+        }
+    }
+
+    fn perform(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::Delete => self.delete_task(),
+            PendingAction::BulkDelete => self.delete_marked(),
+            PendingAction::ArchiveAll => self.archive_completed(),
+            PendingAction::Purge => self.purge_archive(),
+            PendingAction::DeleteProject => self.delete_project(),
+        }
+    }
+
+    /// Describes what a pending action will affect, for the confirmation
+    /// dialog.
+    pub fn confirm_summary(&self, action: PendingAction) -> String {
+        let with_subtasks = |count: usize| match count {
+            0 => String::new(),
+            1 => " and 1 subtask".to_string(),
+            n => format!(" and {} subtasks", n),
+        };
+        match action {
+            PendingAction::Delete => match self.selected_row() {
+                Some(row) => {
+                    let task = self.row_task(&row);
+                    let description = task.map_or("", |t| t.description.as_str());
+                    let subtasks = task.map_or(0, Task::subtask_count);
+                    let permanently = if row.archived { "Permanently delete" } else { "Delete" };
+                    format!("{} \"{}\"{}?", permanently, description, with_subtasks(subtasks))
+                }
+                None => "Nothing selected".to_string(),
+            },
+            PendingAction::BulkDelete => {
+                let paths = self.target_paths();
+                // Marked subtasks of marked tasks are only counted once
+                let is_nested = |path: &Vec<usize>| {
+                    paths
+                        .iter()
+                        .any(|other| other.len() < path.len() && path.starts_with(other))
+                };
+                let subtasks: usize = paths
+                    .iter()
+                    .filter(|path| !is_nested(path))
+                    .filter_map(|path| self.task(path))
+                    .map(|task| 1 + task.subtask_count())
+                    .sum::<usize>()
+                    .saturating_sub(paths.len());
+                format!("Delete {} marked task(s){}?", paths.len(), with_subtasks(subtasks))
+            }
+            PendingAction::ArchiveAll => {
+                let count = self.tasks.iter().filter(|task| task.is_closed()).count();
+                format!("Archive {} completed task(s)?", count)
+            }
+            PendingAction::Purge => {
+                let subtasks = self.archive.iter().map(Task::subtask_count).sum();
+                format!(
+                    "Permanently delete {} archived task(s){}?",
+                    self.archive.len(),
+                    with_subtasks(subtasks)
+                )
+            }
+            PendingAction::DeleteProject => match self.project_filter {
+                ProjectFilter::Project(id) => {
+                    let name = self.project(id).map_or("", |project| project.name.as_str());
+                    let count = self.tasks.iter().chain(&self.archive).filter(|task| task.project == Some(id)).count();
+                    format!("Delete project \"{}\"? Its {} task(s) move up a level.", name, count)
+                }
+                _ => "Switch to a project first".to_string(),
+            },
         }
     }
+
+    pub fn purge_archive(&mut self) {
+        self.checkpoint();
+        let count = self.archive.len();
+        self.archive.clear();
+        self.status_message = Some(format!("Purged {} archived task(s)", count));
+        self.clamp_selection();
+    }
+
+    /// Moves every completed top-level task into the archive.
+    pub fn archive_completed(&mut self) {
+        self.checkpoint();
+        let count = self.archive_where(|_| true);
+        self.status_message = Some(format!("Archived {} completed task(s)", count));
+        self.clamp_selection();
+    }
+
+    /// Moves completed top-level tasks accepted by `filter` into the archive
+    /// and returns how many moved.
+    fn archive_where(&mut self, filter: impl Fn(&Task) -> bool) -> usize {
+        let (archived, kept): (Vec<Task>, Vec<Task>) = self
+            .tasks
+            .drain(..)
+            .partition(|task| task.is_closed() && filter(task));
+        self.tasks = kept;
+        let count = archived.len();
+        self.archive.extend(archived);
+        count
+    }
+
+    /// Moves the archived task under the cursor, with its subtasks, back
+    /// to the end of the task list.
+    pub fn restore_task(&mut self) {
+        let Some(row) = self.selected_row().filter(|row| row.archived) else {
+            return;
+        };
+        self.checkpoint();
+        let mut task = self.archive.remove(row.path[0]);
+        task.touch();
+        self.status_message = Some(format!("Restored \"{}\"", task.description));
+        self.tasks.push(task);
+        self.clamp_selection();
+    }
+
+    fn clamp_selection(&mut self) {
+        let len = self.visible_rows().len();
+        self.state.select(match self.state.selected() {
+            _ if len == 0 => None,
+            Some(i) => Some(i.min(len - 1)),
+            None => Some(0),
+        });
+    }
+
+    pub fn add_task(&mut self) {
+        self.checkpoint();
+        if self.adding_subtask {
+            self.add_sub_task();
+            self.adding_subtask = false;
+        } else {
+            let mut new_task = self.task_from_input();
+            if let ProjectFilter::Project(id) = self.project_filter {
+                new_task.project = Some(id);
+            }
+            self.tasks.push(new_task);
+        }
+        self.leave_input();
+    }
+
+    pub fn add_sub_task(&mut self) {
+        if let Some(path) = self.selected_path() {
+            let new_task = self.task_from_input();
+            if let Some(parent) = self.task_mut(&path) {
+                parent.sub_tasks.push(new_task);
+                parent.touch();
+            }
+        }
+        self.leave_input();
+    }
+
+    /// Builds a task from `input`. Drains `input`.
+    fn task_from_input(&mut self) -> Task {
+        new_task(self.next_id(), self.input.drain(..).collect())
+    }
+
+    /// An ID not used by any task, archived ones included. IDs still listed
+    /// as blockers of a task are skipped too, even once deleted, so that a
+    /// new task never takes over a dependency.
+    fn next_id(&self) -> usize {
+        let ids = max_id(&self.tasks).max(max_id(&self.archive));
+        ids.max(max_blocker_id(&self.tasks)).max(max_blocker_id(&self.archive)) + 1
+    }
+
+    /// The top-level tasks in the current view, by index, in manual order.
+    fn document_roots(&self) -> Vec<usize> {
+        let mut roots: Vec<usize> = self
+            .visible_rows()
+            .iter()
+            .filter(|row| !row.archived)
+            .map(|row| row.path[0])
+            .collect();
+        roots.sort_unstable();
+        roots.dedup();
+        roots
+    }
+
+    /// The top-level tasks in the current view, with all their subtasks, as
+    /// an editable checklist.
+    pub fn document(&self) -> String {
+        render_document(self.document_roots().into_iter().map(|i| &self.tasks[i]))
+    }
+
+    /// Saves the current search, sort order and list under `name`,
+    /// replacing any view of that name.
+    pub fn save_view(&mut self, name: &str) {
+        let view = SavedView {
+            name: name.to_string(),
+            search: self.search_input.clone(),
+            sort_mode: self.sort_mode,
+            archive: self.view == View::Archive,
+        };
+        match self.views.iter_mut().find(|v| v.name == name) {
+            Some(existing) => *existing = view,
+            None => self.views.push(view),
+        }
+        self.status_message = Some(format!("Saved view: {}", name));
+    }
+
+    pub fn apply_view(&mut self, name: &str) {
+        let Some(view) = self.views.iter().find(|v| v.name == name).cloned() else {
+            self.status_message = Some(format!("Unknown view: {}", name));
+            return;
+        };
+        self.search_input = view.search;
+        self.sort_mode = view.sort_mode;
+        self.set_view(if view.archive { View::Archive } else { View::Tasks });
+    }
+
+    pub fn delete_view(&mut self, name: &str) {
+        let before = self.views.len();
+        self.views.retain(|v| v.name != name);
+        self.status_message = Some(if self.views.len() < before {
+            format!("Deleted view: {}", name)
+        } else {
+            format!("Unknown view: {}", name)
+        });
+    }
+
+    pub fn project(&self, id: usize) -> Option<&Project> {
+        self.projects.iter().find(|project| project.id == id)
+    }
+
+    /// The project of a top-level task, if it still exists.
+    pub fn task_project(&self, task: &Task) -> Option<&Project> {
+        self.project(task.project?)
+    }
+
+    fn project_named(&self, name: &str) -> Option<usize> {
+        self.projects
+            .iter()
+            .find(|project| project.name.eq_ignore_ascii_case(name))
+            .map(|project| project.id)
+    }
+
+    /// Projects in sidebar order, each followed by those nested under it,
+    /// with their depth.
+    pub fn project_tree(&self) -> Vec<(usize, &Project)> {
+        let mut tree = Vec::new();
+        for project in self.projects.iter() {
+            if project.parent.is_none_or(|id| self.project(id).is_none()) {
+                self.push_project(project, 0, &mut tree);
+            }
+        }
+        tree
+    }
+
+    fn push_project<'a>(&'a self, project: &'a Project, depth: usize, tree: &mut Vec<(usize, &'a Project)>) {
+        tree.push((depth, project));
+        for child in self.projects.iter().filter(|p| p.parent == Some(project.id)) {
+            self.push_project(child, depth + 1, tree);
+        }
+    }
+
+    /// The IDs of a project and every project nested under it.
+    fn project_subtree(&self, id: usize) -> HashSet<usize> {
+        let mut ids = HashSet::from([id]);
+        let mut grew = true;
+        while grew {
+            grew = false;
+            for project in self.projects.iter() {
+                if project.parent.is_some_and(|parent| ids.contains(&parent)) && ids.insert(project.id) {
+                    grew = true;
+                }
+            }
+        }
+        ids
+    }
+
+    /// Whether a top-level task shows up under `filter`.
+    pub fn project_matcher(&self, filter: ProjectFilter) -> impl Fn(&Task) -> bool + '_ {
+        let subtree = match filter {
+            ProjectFilter::Project(id) => self.project_subtree(id),
+            _ => HashSet::new(),
+        };
+        move |task: &Task| match filter {
+            ProjectFilter::All => true,
+            ProjectFilter::Inbox => self.task_project(task).is_none(),
+            ProjectFilter::Project(_) => task.project.is_some_and(|id| subtree.contains(&id)),
+        }
+    }
+
+    /// Open tasks, subtasks included, among the tasks `filter` shows.
+    pub fn open_count(&self, filter: ProjectFilter) -> usize {
+        let in_project = self.project_matcher(filter);
+        self.tasks
+            .iter()
+            .filter(|task| in_project(task))
+            .map(open_tasks)
+            .sum()
+    }
+
+    /// Every filter in sidebar order.
+    pub fn project_filters(&self) -> Vec<ProjectFilter> {
+        [ProjectFilter::All, ProjectFilter::Inbox]
+            .into_iter()
+            .chain(
+                self.project_tree()
+                    .into_iter()
+                    .map(|(_, project)| ProjectFilter::Project(project.id)),
+            )
+            .collect()
+    }
+
+    pub fn project_filter_name(&self, filter: ProjectFilter) -> String {
+        match filter {
+            ProjectFilter::All => "All".to_string(),
+            ProjectFilter::Inbox => "Inbox".to_string(),
+            ProjectFilter::Project(id) => self.project(id).map_or(String::new(), |p| p.name.clone()),
+        }
+    }
+
+    pub fn set_project_filter(&mut self, filter: ProjectFilter) {
+        self.project_filter = filter;
+        let rows = self.visible_rows();
+        self.state.select(if rows.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn cycle_project(&mut self, forward: bool) {
+        let filters = self.project_filters();
+        let i = filters.iter().position(|f| *f == self.project_filter).unwrap_or(0);
+        let next = if forward {
+            (i + 1) % filters.len()
+        } else {
+            (i + filters.len() - 1) % filters.len()
+        };
+        self.set_project_filter(filters[next]);
+    }
+
+    pub fn toggle_sidebar(&mut self) {
+        self.show_sidebar = !self.show_sidebar;
+    }
+
+    /// Switches to the project called `name`, or to `all` or `inbox`.
+    pub fn switch_project(&mut self, name: &str) {
+        let filter = match name.to_lowercase().as_str() {
+            "all" => ProjectFilter::All,
+            "inbox" => ProjectFilter::Inbox,
+            _ => match self.project_named(name) {
+                Some(id) => ProjectFilter::Project(id),
+                None => {
+                    self.status_message = Some(format!("Unknown project: {}", name));
+                    return;
+                }
+            },
+        };
+        self.set_project_filter(filter);
+    }
+
+    /// Creates a project nested under the current one, if any, and
+    /// switches to it.
+    pub fn add_project(&mut self, name: &str) {
+        if let Err(e) = self.check_project_name(name) {
+            self.status_message = Some(e);
+            return;
+        }
+        let id = self.projects.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+        let parent = match self.project_filter {
+            ProjectFilter::Project(parent) => Some(parent),
+            _ => None,
+        };
+        self.checkpoint();
+        self.projects.push(Project {
+            id,
+            name: name.to_string(),
+            color: None,
+            parent,
+        });
+        self.show_sidebar = true;
+        self.set_project_filter(ProjectFilter::Project(id));
+    }
+
+    /// A new name for a project must not be blank, taken, or one of the
+    /// names `:project` keeps for the inbox and all tasks.
+    fn check_project_name(&self, name: &str) -> Result<(), String> {
+        if name.trim().is_empty() {
+            Err("A project needs a name".to_string())
+        } else if ["all", "inbox"].contains(&name.to_lowercase().as_str()) || self.project_named(name).is_some() {
+            Err(format!("A project called {} already exists", name))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The project being shown, for commands that change it. Saves an undo
+    /// checkpoint first.
+    fn current_project_mut(&mut self) -> Option<&mut Project> {
+        let ProjectFilter::Project(id) = self.project_filter else {
+            self.status_message = Some("Switch to a project first".to_string());
+            return None;
+        };
+        self.project(id)?;
+        self.checkpoint();
+        self.projects.iter_mut().find(|project| project.id == id)
+    }
+
+    pub fn rename_project(&mut self, name: &str) {
+        if let Err(e) = self.check_project_name(name) {
+            self.status_message = Some(e);
+        } else if let Some(project) = self.current_project_mut() {
+            project.name = name.to_string();
+        }
+    }
+
+    /// Sets the current project's color, or clears it with `none`.
+    pub fn set_project_color(&mut self, color: &str) {
+        let color = match color {
+            "none" => None,
+            _ if Color::from_str(color).is_ok() => Some(color.to_string()),
+            _ => {
+                self.status_message = Some(format!("Invalid color: {}", color));
+                return;
+            }
+        };
+        if let Some(project) = self.current_project_mut() {
+            project.color = color;
+        }
+    }
+
+    /// Nests the current project under the one called `name`, or moves it
+    /// to the top level with `none`.
+    pub fn set_project_parent(&mut self, name: &str) {
+        let ProjectFilter::Project(id) = self.project_filter else {
+            self.status_message = Some("Switch to a project first".to_string());
+            return;
+        };
+        let parent = match name {
+            "none" => None,
+            _ => match self.project_named(name) {
+                Some(parent) if self.project_subtree(id).contains(&parent) => {
+                    self.status_message = Some("A project cannot be nested inside itself".to_string());
+                    return;
+                }
+                Some(parent) => Some(parent),
+                None => {
+                    self.status_message = Some(format!("Unknown project: {}", name));
+                    return;
+                }
+            },
+        };
+        if let Some(project) = self.current_project_mut() {
+            project.parent = parent;
+        }
+    }
+
+    /// Deletes the current project. Its tasks and nested projects move up
+    /// to its parent.
+    pub fn delete_project(&mut self) {
+        let ProjectFilter::Project(id) = self.project_filter else {
+            self.status_message = Some("Switch to a project first".to_string());
+            return;
+        };
+        let Some(parent) = self.project(id).map(|p| p.parent) else {
+            return;
+        };
+        self.checkpoint();
+        for task in self.tasks.iter_mut().chain(self.archive.iter_mut()) {
+            if task.project == Some(id) {
+                task.project = parent;
+            }
+        }
+        for project in self.projects.iter_mut() {
+            if project.parent == Some(id) {
+                project.parent = parent;
+            }
+        }
+        self.projects.retain(|project| project.id != id);
+        self.set_project_filter(parent.map_or(ProjectFilter::All, ProjectFilter::Project));
+    }
+
+    /// Moves the target tasks to the project called `name`, or out of any
+    /// project with `inbox`. Subtasks become top-level tasks there.
+    pub fn move_to_project(&mut self, name: &str) {
+        let project = match name.to_lowercase().as_str() {
+            "inbox" => None,
+            _ => match self.project_named(name) {
+                Some(id) => Some(id),
+                None => {
+                    self.status_message = Some(format!("Unknown project: {}", name));
+                    return;
+                }
+            },
+        };
+        let mut paths = self.target_paths();
+        if paths.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        // Deepest and last first, so earlier paths stay valid
+        paths.sort_by(|a, b| b.cmp(a));
+        let mut moved = 0;
+        for path in &paths {
+            if path.len() == 1 {
+                self.tasks[path[0]].project = project;
+                self.tasks[path[0]].touch();
+            } else if let Some(mut task) = remove_task(&mut self.tasks, path) {
+                task.project = project;
+                task.touch();
+                self.tasks.push(task);
+            } else {
+                continue;
+            }
+            moved += 1;
+        }
+        self.marked.clear();
+        self.status_message = Some(format!(
+            "Moved {} task{} to {}",
+            moved,
+            if moved == 1 { "" } else { "s" },
+            project.map_or("Inbox".to_string(), |id| self.project_filter_name(ProjectFilter::Project(id)))
+        ));
+        self.clamp_selection();
+        self.leave_input();
+    }
+
+    /// Writes the tasks in the current view to `path`: each top-level task
+    /// with a visible row, along with all its subtasks, or in the stats view
+    /// every task of the project shown.
+    pub fn export(&mut self, format: ExportFormat, path: &str) {
+        let tasks: Vec<&Task> = if self.view == View::Stats {
+            let in_project = self.project_matcher(self.project_filter);
+            self.tasks.iter().filter(|task| in_project(task)).collect()
+        } else {
+            let mut roots: Vec<(bool, usize)> = Vec::new();
+            for row in self.visible_rows() {
+                if !roots.contains(&(row.archived, row.path[0])) {
+                    roots.push((row.archived, row.path[0]));
+                }
+            }
+            roots
+                .into_iter()
+                .map(|(archived, i)| if archived { &self.archive[i] } else { &self.tasks[i] })
+                .collect()
+        };
+        let count: usize = tasks.iter().map(|task| task.with_subtasks().len()).sum();
+        let text = match format {
+            ExportFormat::Markdown => Ok(export_markdown(tasks.iter().copied())),
+            ExportFormat::Json => serde_json::to_string_pretty(&tasks).map_err(io::Error::other),
+        };
+        self.status_message = Some(match text.and_then(|text| fs::write(path, text)) {
+            Ok(()) => format!("Exported {} tasks to {}", count, path),
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+
+    /// Applies an edited `document()` to the task tree. Lines are matched to
+    /// tasks by their ID markers: unmatched lines become new tasks, tasks
+    /// left without a line are deleted, and the document order becomes the
+    /// manual order. Tasks whose line is unchanged keep their timestamps.
+    pub fn apply_document(&mut self, text: &str) {
+        let roots = self.document_roots();
+        let parsed = parse_document(text);
+        let mut next_id = self.next_id();
+        self.checkpoint();
+
+        // The edited tasks go back where the first of them was
+        let position = roots.first().copied().unwrap_or(self.tasks.len());
+        let mut originals = HashMap::new();
+        for &i in roots.iter().rev() {
+            index_tasks(self.tasks.remove(i), &mut originals);
+        }
+
+        let mut changes = DocumentChanges::default();
+        let mut edited = build_tasks(parsed, &mut originals, &mut next_id, &mut changes);
+        // New and promoted tasks join the project being edited
+        if let ProjectFilter::Project(id) = self.project_filter {
+            for task in edited.iter_mut().filter(|task| task.project.is_none()) {
+                task.project = Some(id);
+            }
+        }
+        changes.deleted = originals.len();
+        self.tasks.splice(position..position, edited);
+
+        self.status_message = Some(format!(
+            "{} added, {} edited, {} deleted",
+            changes.added, changes.edited, changes.deleted
+        ));
+        self.clamp_selection();
+    }
+
+    /// Sets the target tasks' due date. Dates like `friday` or `next week`
+    /// are stored as the day they fall on; anything else is kept as typed.
+    pub fn set_due_date(&mut self) {
+        let input: String = self.date_input.drain(..).collect();
+        let due_date = parse_date_string(&input, Local::now(), Dialect::Us)
+            .map_or(input, |date| date.format("%Y-%m-%d").to_string());
+        let paths = self.target_paths();
+        if !paths.is_empty() {
+            self.checkpoint();
+            for path in &paths {
+                if let Some(task) = self.task_mut(path) {
+                    task.due_date = Some(due_date.clone());
+                    task.touch();
+                }
+            }
+        }
+        self.leave_input();
+    }
+
+    /// Deletes every marked task, with its subtasks.
+    pub fn delete_marked(&mut self) {
+        if self.marked.is_empty() {
+            return;
+        }
+        self.checkpoint();
+        // Deepest and last first, so earlier paths stay valid
+        let mut paths = self.target_paths();
+        paths.sort_by(|a, b| b.cmp(a));
+        for path in &paths {
+            remove_task(&mut self.tasks, path);
+        }
+        self.marked.clear();
+        self.clamp_selection();
+    }
+
+    /// Deletes the task under the cursor, with its subtasks. Marks are
+    /// left alone.
+    pub fn delete_task(&mut self) {
+        if let Some(row) = self.selected_row() {
+            self.checkpoint();
+            let removed = if row.archived {
+                remove_task(&mut self.archive, &row.path)
+            } else {
+                let removed = remove_task(&mut self.tasks, &row.path);
+                if let Some(parent) = self.task_mut(&row.path[..row.path.len() - 1]) {
+                    parent.touch();
+                }
+                removed
+            };
+            for task in removed.iter().flat_map(Task::with_subtasks) {
+                self.marked.remove(&task.id);
+            }
+
+            // Update selection
+            self.clamp_selection();
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.move_within_siblings(-1);
+    }
+
+    pub fn move_down(&mut self) {
+        self.move_within_siblings(1);
+    }
+
+    /// Reordering edits the manual order, so any other sort mode is switched
+    /// off first. The cursor stays on the selected task. Also records an
+    /// undo checkpoint for the move, so it is only called once the move is
+    /// known to change something.
+    fn start_reorder(&mut self, path: &[usize]) {
+        self.checkpoint();
+        self.sort_mode = SortMode::Manual;
+        self.select_path(path);
+    }
+
+    /// Swaps the selected task with its neighbour.
+    fn move_within_siblings(&mut self, offset: isize) {
+        let Some(mut path) = self.selected_path() else {
+            return;
+        };
+        let (last, parent) = (path[path.len() - 1], path[..path.len() - 1].to_vec());
+        let count = self.siblings_mut(&parent).map_or(0, |siblings| siblings.len());
+        let Some(target) = last.checked_add_signed(offset).filter(|&i| i < count) else {
+            return;
+        };
+        self.start_reorder(&path);
+        let siblings = self.siblings_mut(&parent).unwrap();
+        siblings.swap(last, target);
+        siblings[target].touch();
+        *path.last_mut().unwrap() = target;
+        self.select_path(&path);
+    }
+
+    /// Makes the selected task the last subtask of the sibling above it.
+    pub fn indent(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        let (&last, parent) = path.split_last().unwrap();
+        if last == 0 {
+            return;
+        }
+        self.start_reorder(&path);
+        let Some(siblings) = self.siblings_mut(parent) else {
+            return;
+        };
+        let mut task = siblings.remove(last);
+        task.touch();
+        let new_parent = &mut siblings[last - 1].sub_tasks;
+        new_parent.push(task);
+
+        let mut new_path = parent.to_vec();
+        new_path.extend([last - 1, new_parent.len() - 1]);
+        self.select_path(&new_path);
+    }
+
+    /// Moves the selected subtask out of its parent, placing it right after
+    /// the parent.
+    pub fn outdent(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        if path.len() < 2 {
+            return;
+        }
+        self.start_reorder(&path);
+        let (&last, parent) = path.split_last().unwrap();
+        let (&parent_index, grandparent) = parent.split_last().unwrap();
+        let project = self.tasks[path[0]].project;
+        let Some(mut task) = self.task_mut(parent).map(|p| p.sub_tasks.remove(last)) else {
+            return;
+        };
+        // A task moved to the top level stays in its project
+        task.project = project;
+        task.touch();
+        if let Some(siblings) = self.siblings_mut(grandparent) {
+            siblings.insert(parent_index + 1, task);
+        }
+
+        let mut new_path = grandparent.to_vec();
+        new_path.push(parent_index + 1);
+        self.select_path(&new_path);
+    }
+
+    fn task_matches(task: &Task, search_lower: &str) -> bool {
+        // Filter by description (case-insensitive)
+        task.description.to_lowercase().contains(search_lower)
+            // Filter by tags (case-insensitive)
+            || task.tags.iter().any(|tag| tag.to_lowercase().contains(search_lower))
+            // Filter by priority
+            || match search_lower {
+                "high" | "h" => matches!(task.priority, Priority::High),
+                "medium" | "med" | "m" => matches!(task.priority, Priority::Medium),
+                "low" | "l" => matches!(task.priority, Priority::Low),
+                _ => false,
+            }
+            // Filter by status
+            || task.has_status(search_lower)
+            // Filter by what a waiting task is waiting on
+            || task.waiting_on.as_ref().is_some_and(|reason| reason.to_lowercase().contains(search_lower))
+            // Filter by notes
+            || task.notes.to_lowercase().contains(search_lower)
+            // Filter by due date (if it exists)
+            || task.due_date.as_ref().is_some_and(|date| date.contains(search_lower))
+            // Filter by subtasks content
+            || task.sub_tasks.iter().any(|subtask| Self::task_matches(subtask, search_lower))
+    }
+
+    /// The rows of the task list, in display order. Top-level tasks are
+    /// filtered by the search query, and every level of
+    /// the tree is ordered by the current sort mode. The archive view lists
+    /// archived tasks instead, and a search can optionally take them in too.
+    pub fn visible_rows(&self) -> Vec<Row> {
+        let search_lower = Some(self.search_input.to_lowercase()).filter(|s| !s.is_empty());
+        let today = Local::now().format("%Y-%m-%d").to_string();
+
+        let mut rows = Vec::new();
+        if self.view == View::Next {
+            return self.next_action_rows(search_lower.as_deref(), &today);
+        }
+        if self.view == View::Agenda {
+            return self.agenda_rows(search_lower.as_deref(), &today, self.agenda_day);
+        }
+        if self.view == View::Stats {
+            return rows;
+        }
+        if self.view != View::Archive {
+            self.push_top_level_rows(&self.tasks, false, search_lower.as_deref(), &today, &mut rows);
+        }
+        let with_archive = self.view == View::Tasks && self.search_archive && search_lower.is_some();
+        if self.view == View::Archive || with_archive {
+            self.push_top_level_rows(&self.archive, true, search_lower.as_deref(), &today, &mut rows);
+        }
+        rows
+    }
+
+    fn push_top_level_rows(
+        &self,
+        tasks: &[Task],
+        archived: bool,
+        search_lower: Option<&str>,
+        today: &str,
+        rows: &mut Vec<Row>,
+    ) {
+        let in_project = self.project_matcher(self.project_filter);
+        for i in self.sorted_indices(tasks, today) {
+            let task = &tasks[i];
+            if !in_project(task) {
+                continue;
+            }
+            if search_lower.is_some_and(|search| !Self::task_matches(task, search)) {
+                continue;
+            }
+            let focused = match self.focus {
+                _ if archived => true,
+                Focus::Off => true,
+                Focus::On => !task.is_closed(),
+                Focus::Single(id) => task.id == id,
+            };
+            if !focused {
+                continue;
+            }
+            // The board only has cards for top-level tasks
+            if self.view == View::Board {
+                rows.push(Row { path: vec![i], archived });
+            } else {
+                self.push_rows(task, vec![i], archived, today, rows);
+            }
+        }
+    }
+
+    /// The rows of the next actions view, as a flat list in sort order.
+    fn next_action_rows(&self, search_lower: Option<&str>, today: &str) -> Vec<Row> {
+        let in_project = self.project_matcher(self.project_filter);
+        let mut paths = Vec::new();
+        collect_paths(&self.tasks, &mut Vec::new(), &|task| self.is_next_action(task), &mut paths);
+        let mut rows: Vec<Row> = paths
+            .into_iter()
+            .filter(|path| in_project(&self.tasks[path[0]]))
+            .filter(|path| {
+                search_lower.is_none_or(|search| self.task(path).is_some_and(|task| Self::task_matches(task, search)))
+            })
+            .map(|path| Row { path, archived: false })
+            .collect();
+        rows.sort_by(|a, b| match (self.task(&a.path), self.task(&b.path)) {
+            (Some(a), Some(b)) => self.sort_mode.compare(a, b, today),
+            _ => Ordering::Equal,
+        });
+        rows
+    }
+
+    /// The rows of the agenda: open tasks from every level of the tree with
+    /// a due date, on `day` if given, ordered by due date and then by the
+    /// sort mode.
+    fn agenda_rows(&self, search_lower: Option<&str>, today: &str, day: Option<NaiveDate>) -> Vec<Row> {
+        let in_project = self.project_matcher(self.project_filter);
+        let mut paths = Vec::new();
+        let is_due = |task: &Task| {
+            !task.is_closed() && agenda::due_date(task).is_some_and(|due| day.is_none_or(|day| due == day))
+        };
+        collect_paths(&self.tasks, &mut Vec::new(), &is_due, &mut paths);
+        let mut rows: Vec<Row> = paths
+            .into_iter()
+            .filter(|path| in_project(&self.tasks[path[0]]))
+            .filter(|path| {
+                search_lower.is_none_or(|search| self.task(path).is_some_and(|task| Self::task_matches(task, search)))
+            })
+            .map(|path| Row { path, archived: false })
+            .collect();
+        rows.sort_by(|a, b| match (self.task(&a.path), self.task(&b.path)) {
+            (Some(a), Some(b)) => agenda::due_date(a)
+                .cmp(&agenda::due_date(b))
+                .then_with(|| self.sort_mode.compare(a, b, today)),
+            _ => Ordering::Equal,
+        });
+        rows
+    }
+
+    /// How many agenda tasks are due on each day, whichever day is picked.
+    pub fn agenda_counts(&self) -> HashMap<NaiveDate, usize> {
+        let search_lower = Some(self.search_input.to_lowercase()).filter(|s| !s.is_empty());
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let mut counts = HashMap::new();
+        for row in self.agenda_rows(search_lower.as_deref(), &today, None) {
+            if let Some(due) = self.row_task(&row).and_then(agenda::due_date) {
+                *counts.entry(due).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Narrows the agenda to `day`, or shows every day again with `None`.
+    pub fn select_day(&mut self, day: Option<NaiveDate>) {
+        self.agenda_day = day;
+        if let Some(day) = day {
+            self.agenda_month = day.with_day(1).unwrap();
+        }
+        let rows = self.visible_rows();
+        self.state.select(if rows.is_empty() { None } else { Some(0) });
+    }
+
+    /// Picks the day `offset` days from the picked one. Without a picked
+    /// day it starts from today, or from the 1st of another month shown.
+    pub fn step_day(&mut self, offset: i64) {
+        let today = Local::now().date_naive();
+        let day = self.agenda_day.unwrap_or(if self.agenda_month == today.with_day(1).unwrap() {
+            today
+        } else {
+            self.agenda_month
+        });
+        self.select_day(Some(day + chrono::Duration::days(offset)));
+    }
+
+    /// Shows the month `offset` months from the one shown, moving the picked
+    /// day along with it.
+    pub fn step_month(&mut self, offset: i32) {
+        let months = chrono::Months::new(offset.unsigned_abs());
+        let shift = |date: NaiveDate| {
+            if offset < 0 {
+                date.checked_sub_months(months)
+            } else {
+                date.checked_add_months(months)
+            }
+        };
+        match self.agenda_day {
+            Some(day) => {
+                if let Some(day) = shift(day) {
+                    self.select_day(Some(day));
+                }
+            }
+            None => {
+                if let Some(month) = shift(self.agenda_month) {
+                    self.agenda_month = month;
+                }
+            }
+        }
+    }
+
+    fn is_next_action(&self, task: &Task) -> bool {
+        !task.is_closed()
+            && task.status != Status::Waiting
+            && !self.is_blocked(task)
+            && task.sub_tasks.iter().all(|sub_task| sub_task.is_closed())
+    }
+
+    fn push_rows(&self, task: &Task, path: Vec<usize>, archived: bool, today: &str, rows: &mut Vec<Row>) {
+        let children = self.sorted_indices(&task.sub_tasks, today);
+        let hide_completed = !archived && self.focus != Focus::Off;
+        rows.push(Row { path: path.clone(), archived });
+        for i in children {
+            if hide_completed && task.sub_tasks[i].is_closed() {
+                continue;
+            }
+            let mut child_path = path.clone();
+            child_path.push(i);
+            self.push_rows(&task.sub_tasks[i], child_path, archived, today, rows);
+        }
+    }
+
+    fn sorted_indices(&self, tasks: &[Task], today: &str) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..tasks.len()).collect();
+        indices.sort_by(|&a, &b| self.sort_mode.compare(&tasks[a], &tasks[b], today));
+        indices
+    }
+
+    /// The index of the row drawn at `position`, if any.
+    pub fn row_at(&self, position: Position) -> Option<usize> {
+        if let Some((area, rows)) = self.areas.board.iter().find(|(area, _)| area.contains(position)) {
+            return rows.get(usize::from(position.y - area.y)).copied();
+        }
+        if !self.areas.list.contains(position) {
+            return None;
+        }
+        if self.view == View::Agenda {
+            return self.areas.agenda.get(usize::from(position.y - self.areas.list.y)).copied().flatten();
+        }
+        let row = self.state.offset() + usize::from(position.y - self.areas.list.y);
+        (row < self.visible_rows().len()).then_some(row)
+    }
+
+    pub fn selected_row(&self) -> Option<Row> {
+        let selected_index = self.state.selected()?;
+        self.visible_rows().into_iter().nth(selected_index)
+    }
+
+    /// The path of the selected task. Archived tasks are read-only, so this
+    /// is `None` when the cursor is on one.
+    pub fn selected_path(&self) -> Option<Vec<usize>> {
+        self.selected_row().filter(|row| !row.archived).map(|row| row.path)
+    }
+
+    pub fn selected_task(&self) -> Option<&Task> {
+        self.row_task(&self.selected_row()?)
+    }
+
+    /// The selected task, unless it is archived.
+    pub fn editable_task(&self) -> Option<&Task> {
+        self.task(&self.selected_path()?)
+    }
+
+    /// Selects the task with this ID, if the list shows it.
+    pub fn select_id(&mut self, id: usize) -> bool {
+        let row = self
+            .visible_rows()
+            .iter()
+            .position(|row| !row.archived && self.row_task(row).is_some_and(|task| task.id == id));
+        if row.is_some() {
+            self.state.select(row);
+        }
+        row.is_some()
+    }
+
+    fn select_path(&mut self, path: &[usize]) {
+        if let Some(i) = self
+            .visible_rows()
+            .iter()
+            .position(|row| !row.archived && row.path == path)
+        {
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn row_task(&self, row: &Row) -> Option<&Task> {
+        if row.archived {
+            find_task(&self.archive, &row.path)
+        } else {
+            self.task(&row.path)
+        }
+    }
+
+    /// The project of a row's top-level task, which its subtasks share.
+    pub fn row_project(&self, row: &Row) -> Option<&Project> {
+        let top = Row {
+            path: row.path[..1].to_vec(),
+            archived: row.archived,
+        };
+        self.task_project(self.row_task(&top)?)
+    }
+
+    pub fn task(&self, path: &[usize]) -> Option<&Task> {
+        find_task(&self.tasks, path)
+    }
+
+    fn task_mut(&mut self, path: &[usize]) -> Option<&mut Task> {
+        find_task_mut(&mut self.tasks, path)
+    }
+
+    /// The list holding the children of the task at `parent`, or the
+    /// top-level list when `parent` is empty.
+    fn siblings_mut(&mut self, parent: &[usize]) -> Option<&mut Vec<Task>> {
+        if parent.is_empty() {
+            Some(&mut self.tasks)
+        } else {
+            self.task_mut(parent).map(|task| &mut task.sub_tasks)
+        }
+    }
+}
+
+/// Looks a theme up by name, ignoring case and the accent in "Frappé".
+fn find_theme<'a>(themes: &'a [Theme], name: &str) -> Option<&'a Theme> {
+    let name = name.to_lowercase().replace('é', "e");
+    themes.iter().find(|theme| theme.name.to_lowercase() == name)
+}
+
+fn find_task<'a>(tasks: &'a [Task], path: &[usize]) -> Option<&'a Task> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(tasks.get(*first)?, |task, &i| task.sub_tasks.get(i))
+}
+
+fn find_task_mut<'a>(tasks: &'a mut [Task], path: &[usize]) -> Option<&'a mut Task> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(tasks.get_mut(*first)?, |task, &i| task.sub_tasks.get_mut(i))
+}
+
+fn find_id(tasks: &[Task], id: usize) -> Option<&Task> {
+    tasks
+        .iter()
+        .find_map(|task| if task.id == id { Some(task) } else { find_id(&task.sub_tasks, id) })
+}
+
+fn find_id_mut(tasks: &mut [Task], id: usize) -> Option<&mut Task> {
+    for task in tasks {
+        if task.id == id {
+            return Some(task);
+        }
+        if let Some(found) = find_id_mut(&mut task.sub_tasks, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Open tasks in `task`'s tree, itself included.
+fn open_tasks(task: &Task) -> usize {
+    usize::from(!task.is_closed()) + task.sub_tasks.iter().map(open_tasks).sum::<usize>()
+}
+
+/// Builds a task from a line of user input, picking up a natural-language
+/// due date and any `#tags` it contains.
+fn new_task(id: usize, description: String) -> Task {
+    let due_date = parse_date_string(&description, Local::now(), Dialect::Us)
+        .ok()
+        .map(|date| date.format("%Y-%m-%d").to_string());
+    let tags = description
+        .split_whitespace()
+        .filter(|word| word.starts_with('#'))
+        .map(|word| word.to_string())
+        .collect();
+    let now = Local::now();
+
+    Task {
+        id,
+        description,
+        status: Status::Todo,
+        waiting_on: None,
+        blocked_by: Vec::new(),
+        time_entries: Vec::new(),
+        pomodoros: 0,
+        priority: Priority::Medium,
+        due_date,
+        sub_tasks: Vec::new(),
+        tags,
+        notes: String::new(),
+        created_at: Some(now),
+        modified_at: Some(now),
+        completed_at: None,
+        project: None,
+    }
+}
+
+#[derive(Default)]
+struct DocumentChanges {
+    added: usize,
+    edited: usize,
+    deleted: usize,
+}
+
+/// Flattens a task tree into `index` by ID, detaching every subtask.
+fn index_tasks(mut task: Task, index: &mut HashMap<usize, Task>) {
+    for sub_task in std::mem::take(&mut task.sub_tasks) {
+        index_tasks(sub_task, index);
+    }
+    index.insert(task.id, task);
+}
+
+fn build_tasks(
+    parsed: Vec<ParsedTask>,
+    originals: &mut HashMap<usize, Task>,
+    next_id: &mut usize,
+    changes: &mut DocumentChanges,
+) -> Vec<Task> {
+    parsed
+        .into_iter()
+        .map(|line| {
+            let mut task = match line.id.and_then(|id| originals.remove(&id)) {
+                Some(task) if task_line(&task) == line.line => task,
+                Some(mut task) => {
+                    changes.edited += 1;
+                    task.description = line.description;
+                    task.priority = line.priority;
+                    task.due_date = line.due_date;
+                    task.tags = line.tags;
+                    if task.is_closed() != line.completed {
+                        task.set_completed(line.completed);
+                    }
+                    task.touch();
+                    task
+                }
+                None => {
+                    changes.added += 1;
+                    let mut task = new_task(*next_id, line.description);
+                    *next_id += 1;
+                    task.priority = line.priority;
+                    task.due_date = line.due_date.or(task.due_date);
+                    task.tags = line.tags;
+                    if line.completed {
+                        task.set_completed(true);
+                    }
+                    task
+                }
+            };
+            task.sub_tasks = build_tasks(line.sub_tasks, originals, next_id, changes);
+            task
+        })
+        .collect()
+}
+
+fn collect_paths(
+    tasks: &[Task],
+    prefix: &mut Vec<usize>,
+    filter: &dyn Fn(&Task) -> bool,
+    paths: &mut Vec<Vec<usize>>,
+) {
+    for (i, task) in tasks.iter().enumerate() {
+        prefix.push(i);
+        if filter(task) {
+            paths.push(prefix.clone());
+        }
+        collect_paths(&task.sub_tasks, prefix, filter, paths);
+        prefix.pop();
+    }
+}
+
+fn remove_task(tasks: &mut Vec<Task>, path: &[usize]) -> Option<Task> {
+    let (&last, parent) = path.split_last()?;
+    let siblings = match parent {
+        [] => tasks,
+        _ => &mut find_task_mut(tasks, parent)?.sub_tasks,
+    };
+    (last < siblings.len()).then(|| siblings.remove(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::picker::{picker_items, PickerItem};
+
+    fn project(id: usize, name: &str, parent: Option<usize>) -> Project {
+        Project {
+            id,
+            name: name.to_string(),
+            color: None,
+            parent,
+        }
+    }
+
+    fn tasks(descriptions: &[&str]) -> Vec<Task> {
+        descriptions
+            .iter()
+            .enumerate()
+            .map(|(i, description)| new_task(i + 1, description.to_string()))
+            .collect()
+    }
+
+    /// Tasks 1 to 3, where 1 waits for 2 and 2 waits for 3.
+    fn chain() -> App {
+        let mut tasks = tasks(&["Ship", "Test", "Build"]);
+        tasks[0].blocked_by = vec![2];
+        tasks[1].blocked_by = vec![3];
+        App::with_tasks(tasks, Vec::new())
+    }
+
+    #[test]
+    fn deleting_the_selected_task_keeps_other_marked_tasks() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam", "Pay rent"]), Vec::new());
+        app.marked = HashSet::from([2, 3]);
+        app.mode = AppMode::Normal;
+        app.state.select(Some(0));
+        app.request(PendingAction::Delete);
+        assert_eq!(app.confirm_summary(PendingAction::Delete), "Delete \"Buy milk\"?");
+        app.confirm();
+        let left: Vec<usize> = app.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(left, [2, 3]);
+
+        app.request(PendingAction::BulkDelete);
+        app.confirm();
+        assert!(app.tasks.is_empty());
+        assert!(app.marked.is_empty());
+    }
+
+    #[test]
+    fn leaving_an_input_with_marks_returns_to_select_mode() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        app.marked = HashSet::from([2]);
+        app.mode = AppMode::Insert;
+        app.input = "Pay rent".to_string();
+        app.add_task();
+        assert!(matches!(app.mode, AppMode::Select));
+        app.start_notes();
+        app.notes_input = "Before Friday".to_string();
+        app.save_notes();
+        assert!(matches!(app.mode, AppMode::Select));
+        app.clear_marks();
+        assert!(matches!(app.mode, AppMode::Normal));
+    }
+
+    #[test]
+    fn moves_that_change_nothing_leave_no_undo_step() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        app.sort_mode = SortMode::Priority;
+        app.select_path(&[0]);
+        app.move_up();
+        app.indent();
+        app.outdent();
+        app.select_path(&[1]);
+        app.move_down();
+        assert!(app.undo_stack.is_empty());
+        assert_eq!(app.sort_mode, SortMode::Priority);
+
+        app.move_up();
+        assert_eq!(app.undo_stack.len(), 1);
+        assert_eq!(app.sort_mode, SortMode::Manual);
+        assert_eq!(app.tasks[0].description, "Call Sam");
+    }
+
+    #[test]
+    fn export_writes_the_tasks_in_view() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam", "Pay rent"]), Vec::new());
+        app.tasks[1].sub_tasks = tasks(&["Find number"]);
+        app.tasks[1].sub_tasks[0].id = 4;
+        app.search_input = "sam".to_string();
+        let path = std::env::temp_dir().join(format!("todo-export-test-{}.md", std::process::id()));
+        app.export(ExportFormat::Markdown, path.to_str().unwrap());
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(text, "- [ ] Call Sam\n  - [ ] Find number\n");
+        assert_eq!(app.status_message, Some(format!("Exported 2 tasks to {}", path.display())));
+    }
+
+    #[test]
+    fn tasks_in_a_removed_state_keep_a_board_column() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        app.tasks[1].status = Status::Custom("review".to_string());
+        app.view = View::Board;
+        let columns = board::columns(&app);
+        let titles: Vec<String> = columns.iter().map(|column| column.group.title()).collect();
+        assert_eq!(titles, ["Todo", "Doing", "Waiting", "review", "Done", "Cancelled"]);
+        assert_eq!(columns[3].rows.len(), 1);
+    }
+
+    #[test]
+    fn several_completions_on_one_day_make_a_one_day_streak() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam", "Pay rent"]), Vec::new());
+        app.tasks[0].set_status(Status::Done);
+        app.tasks[1].set_status(Status::Done);
+        let stats = crate::stats::stats(&app);
+        assert_eq!(stats.completed[crate::stats::DAYS - 1], 2);
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.longest_streak, 1);
+        assert_eq!(stats.open, 1);
+    }
+
+    #[test]
+    fn the_picker_puts_the_best_match_first() {
+        let mut app = App::with_tasks(Vec::new(), Vec::new());
+        app.views.push(SavedView {
+            name: "theses".to_string(),
+            search: String::new(),
+            sort_mode: SortMode::Manual,
+            archive: false,
+        });
+        let items = picker_items(&app, "thm");
+        assert_eq!(items.first(), Some(&PickerItem::Action(Action::Theme)));
+        assert!(!items.contains(&PickerItem::View("theses".to_string())));
+        let items = picker_items(&app, "view thes");
+        assert_eq!(items.first(), Some(&PickerItem::View("theses".to_string())));
+        // Actions that only work inside a prompt are left out
+        assert!(!picker_items(&app, "").contains(&PickerItem::Action(Action::Submit)));
+    }
+
+    #[test]
+    fn a_pomodoro_stops_quietly_when_its_task_is_deleted() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        let mut pomodoro = Pomodoro::start(2, &app.config.pomodoro);
+        pomodoro.ends_at = std::time::Instant::now();
+        app.pomodoro = Some(pomodoro.clone());
+        assert!(app.tick());
+        assert_eq!(app.tasks[1].pomodoros, 1);
+
+        app.pomodoro = Some(pomodoro);
+        app.tasks.remove(1);
+        app.status_message = None;
+        assert!(!app.tick());
+        assert!(app.pomodoro.is_none());
+        assert!(app.status_message.is_none());
+    }
+
+    #[test]
+    fn cycles_are_found_directly_and_through_others() {
+        let app = chain();
+        assert!(app.would_cycle(2, 1));
+        assert!(app.would_cycle(3, 1));
+        assert!(app.would_cycle(3, 2));
+        assert!(!app.would_cycle(1, 3));
+        assert!(!app.would_cycle(1, 2));
+    }
+
+    #[test]
+    fn dependency_edits_add_and_remove_blockers() {
+        let mut app = chain();
+        app.marked = HashSet::from([3]);
+        app.edit_dependencies("none");
+        app.marked = HashSet::from([1]);
+        app.edit_dependencies("#3 +1");
+        assert_eq!(app.status_message.as_deref(), Some("A task cannot wait for itself"));
+        assert_eq!(app.tasks[0].blocked_by, [2, 3]);
+        app.edit_dependencies("-#2");
+        assert_eq!(app.tasks[0].blocked_by, [3]);
+        app.edit_dependencies("none");
+        assert!(app.tasks[0].blocked_by.is_empty());
+    }
+
+    #[test]
+    fn dependency_edits_refuse_cycles_and_report_each_error_once() {
+        let mut app = chain();
+        app.marked = HashSet::from([2, 3]);
+        app.edit_dependencies("1 x");
+        let errors = [
+            "That would make a cycle: #1 already waits for #2",
+            "Not a task ID: x",
+            "That would make a cycle: #1 already waits for #3",
+        ];
+        assert_eq!(app.status_message, Some(errors.join("; ")));
+        app.edit_dependencies("9 x");
+        assert_eq!(app.status_message.as_deref(), Some("No task with ID 9; Not a task ID: x"));
+        assert_eq!(app.tasks[2].blocked_by, Vec::<usize>::new());
+    }
+
+    #[test]
+    fn removing_the_last_blocker_reports_the_task_unblocked() {
+        let mut app = chain();
+        app.marked = HashSet::from([1]);
+        app.edit_dependencies("-2");
+        assert_eq!(app.status_message.as_deref(), Some("Unblocked: Ship"));
+        assert!(!app.is_blocked(&app.tasks[0]));
+    }
+
+    #[test]
+    fn an_unchanged_document_changes_nothing() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        app.tasks[1].due_date = Some("after the launch".to_string());
+        app.tasks[1].sub_tasks = tasks(&["Find number"]);
+        app.tasks[1].sub_tasks[0].id = 3;
+        let document = app.document();
+        app.apply_document(&document);
+        assert_eq!(app.status_message.as_deref(), Some("0 added, 0 edited, 0 deleted"));
+        assert_eq!(app.tasks[1].due_date.as_deref(), Some("after the launch"));
+        assert_eq!(app.tasks[1].sub_tasks.len(), 1);
+    }
+
+    #[test]
+    fn a_renamed_task_is_edited() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        let document = app.document().replace("Buy milk", "Buy oat milk");
+        app.apply_document(&document);
+        assert_eq!(app.status_message.as_deref(), Some("0 added, 1 edited, 0 deleted"));
+        assert_eq!(app.tasks[0].id, 1);
+        assert_eq!(app.tasks[0].description, "Buy oat milk");
+    }
+
+    #[test]
+    fn a_removed_line_deletes_its_task() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        let document: Vec<String> = app
+            .document()
+            .lines()
+            .filter(|line| !line.contains("Buy milk"))
+            .map(str::to_string)
+            .collect();
+        app.apply_document(&document.join("\n"));
+        assert_eq!(app.status_message.as_deref(), Some("0 added, 0 edited, 1 deleted"));
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.tasks[0].description, "Call Sam");
+    }
+
+    #[test]
+    fn an_indented_line_becomes_a_subtask() {
+        let mut app = App::with_tasks(tasks(&["Buy milk", "Call Sam"]), Vec::new());
+        let document = app.document().replace("- [ ] Call Sam", "  - [ ] Call Sam");
+        app.apply_document(&document);
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.tasks[0].sub_tasks.len(), 1);
+        assert_eq!(app.tasks[0].sub_tasks[0].id, 2);
+        assert_eq!(app.tasks[0].sub_tasks[0].description, "Call Sam");
+    }
+
+    #[test]
+    fn projects_cannot_take_reserved_or_blank_names() {
+        let mut app = App::with_tasks(Vec::new(), vec![project(1, "Work", None), project(2, "Home", None)]);
+        app.switch_project("Work");
+        for name in ["All", "inbox", "home", " "] {
+            app.rename_project(name);
+            assert_eq!(app.project(1).map(|p| p.name.as_str()), Some("Work"));
+            app.add_project(name);
+            assert_eq!(app.projects.len(), 2);
+        }
+        assert!(app.undo_stack.is_empty());
+        app.rename_project("Job");
+        assert_eq!(app.project(1).map(|p| p.name.as_str()), Some("Job"));
+    }
+
+    #[test]
+    fn undo_restores_a_deleted_project() {
+        let mut task = new_task(1, "Write the report".to_string());
+        task.project = Some(2);
+        let mut app = App::with_tasks(vec![task], vec![project(1, "Work", None), project(2, "Reports", Some(1))]);
+        app.switch_project("Reports");
+
+        app.request(PendingAction::DeleteProject);
+        assert!(matches!(app.mode, AppMode::Confirm(PendingAction::DeleteProject)));
+        app.confirm();
+        assert!(app.project(2).is_none());
+        assert_eq!(app.tasks[0].project, Some(1));
+        assert!(app.project_filter == ProjectFilter::Project(1));
+
+        app.undo();
+        assert_eq!(app.project(2).map(|p| p.name.as_str()), Some("Reports"));
+        assert_eq!(app.project(2).and_then(|p| p.parent), Some(1));
+        assert_eq!(app.tasks[0].project, Some(2));
+    }
 }
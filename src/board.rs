@@ -0,0 +1,192 @@
+use crate::app::App;
+use crate::task::{Priority, Status, Task};
+use serde::{Deserialize, Serialize};
+
+/// What the board's columns stand for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Grouping {
+    #[default]
+    Status,
+    Priority,
+    Tag,
+}
+
+impl Grouping {
+    pub const ALL: [Grouping; 3] = [Grouping::Status, Grouping::Priority, Grouping::Tag];
+
+    pub fn next(self) -> Grouping {
+        let i = Grouping::ALL.iter().position(|g| *g == self).unwrap_or(0);
+        Grouping::ALL[(i + 1) % Grouping::ALL.len()]
+    }
+
+    /// The name used by the `:board` command.
+    pub fn name(self) -> &'static str {
+        match self {
+            Grouping::Status => "status",
+            Grouping::Priority => "priority",
+            Grouping::Tag => "tag",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Grouping> {
+        Grouping::ALL.into_iter().find(|grouping| grouping.name() == name)
+    }
+}
+
+/// The tasks a column holds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Group {
+    Status(Status),
+    Priority(Priority),
+    /// Tasks with this tag, or with no tags at all.
+    Tag(Option<String>),
+}
+
+impl Group {
+    pub fn title(&self) -> String {
+        match self {
+            Group::Status(status) => status.label().to_string(),
+            Group::Priority(Priority::High) => "High".to_string(),
+            Group::Priority(Priority::Medium) => "Medium".to_string(),
+            Group::Priority(Priority::Low) => "Low".to_string(),
+            Group::Tag(Some(tag)) => tag.clone(),
+            Group::Tag(None) => "Untagged".to_string(),
+        }
+    }
+
+    fn contains(&self, task: &Task) -> bool {
+        match self {
+            Group::Status(status) => task.status == *status,
+            Group::Priority(priority) => task.priority == *priority,
+            Group::Tag(Some(tag)) => task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Group::Tag(None) => task.tags.is_empty(),
+        }
+    }
+
+    /// Moves `task` from the column `from` into this one. A task keeps its
+    /// other tags when moved between tag columns, and loses them all when
+    /// moved to Untagged.
+    pub fn move_task(&self, from: &Group, task: &mut Task) {
+        match self {
+            Group::Status(status) => task.set_status(status.clone()),
+            Group::Priority(priority) => {
+                task.priority = *priority;
+                task.touch();
+            }
+            Group::Tag(Some(tag)) => {
+                let removed = match from {
+                    Group::Tag(Some(old)) => format!("-{} ", old),
+                    _ => String::new(),
+                };
+                task.edit_tags(&format!("{}+{}", removed, tag));
+            }
+            Group::Tag(None) => {
+                task.tags.clear();
+                task.touch();
+            }
+        }
+    }
+}
+
+/// A board column, with the indices of its cards among the visible rows.
+pub struct Column {
+    pub group: Group,
+    pub rows: Vec<usize>,
+}
+
+/// The board's columns, left to right. Tag columns are the tags of the
+/// tasks on the board, and a task with several tags shows in each.
+pub fn columns(app: &App) -> Vec<Column> {
+    let rows = app.visible_rows();
+    let tasks: Vec<Option<&Task>> = rows.iter().map(|row| app.row_task(row)).collect();
+    let groups: Vec<Group> = match app.board_grouping {
+        Grouping::Status => {
+            // States since removed from config.toml still get a column, so
+            // their tasks can be seen and moved out
+            let mut statuses = app.statuses();
+            for task in tasks.iter().flatten() {
+                if !statuses.contains(&task.status) {
+                    statuses.insert(statuses.len() - 2, task.status.clone());
+                }
+            }
+            statuses.into_iter().map(Group::Status).collect()
+        }
+        Grouping::Priority => [Priority::High, Priority::Medium, Priority::Low]
+            .into_iter()
+            .map(Group::Priority)
+            .collect(),
+        Grouping::Tag => {
+            let mut tags: Vec<String> = tasks.iter().flatten().flat_map(|task| task.tags.iter().cloned()).collect();
+            tags.sort_by_key(|tag| tag.to_lowercase());
+            tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+            std::iter::once(Group::Tag(None))
+                .chain(tags.into_iter().map(|tag| Group::Tag(Some(tag))))
+                .collect()
+        }
+    };
+    groups
+        .into_iter()
+        .map(|group| Column {
+            rows: (0..tasks.len())
+                .filter(|&i| tasks[i].is_some_and(|task| group.contains(task)))
+                .collect(),
+            group,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task(tags: &[&str]) -> Task {
+        serde_json::from_value(json!({
+            "id": 1,
+            "description": "Write report",
+            "priority": "Medium",
+            "due_date": null,
+            "sub_tasks": [],
+            "tags": tags,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn moving_across_status_columns_sets_the_status() {
+        let mut task = task(&[]);
+        Group::Status(Status::Done).move_task(&Group::Status(Status::Todo), &mut task);
+        assert_eq!(task.status, Status::Done);
+        assert!(task.completed_at.is_some());
+        Group::Status(Status::Doing).move_task(&Group::Status(Status::Done), &mut task);
+        assert_eq!(task.status, Status::Doing);
+        assert!(task.completed_at.is_none());
+    }
+
+    #[test]
+    fn moving_across_priority_columns_sets_the_priority() {
+        let mut task = task(&[]);
+        Group::Priority(Priority::High).move_task(&Group::Priority(Priority::Medium), &mut task);
+        assert_eq!(task.priority, Priority::High);
+        assert!(Group::Priority(Priority::High).contains(&task));
+    }
+
+    #[test]
+    fn moving_across_tag_columns_swaps_only_that_tag() {
+        let mut task = task(&["#work", "#urgent"]);
+        let work = Group::Tag(Some("#work".to_string()));
+        let home = Group::Tag(Some("#home".to_string()));
+        home.move_task(&work, &mut task);
+        assert_eq!(task.tags, ["#urgent", "#home"]);
+        assert!(!work.contains(&task));
+
+        // From Untagged, a tag is only added
+        let mut untagged = self::task(&[]);
+        home.move_task(&Group::Tag(None), &mut untagged);
+        assert_eq!(untagged.tags, ["#home"]);
+
+        Group::Tag(None).move_task(&home, &mut task);
+        assert!(task.tags.is_empty());
+    }
+}
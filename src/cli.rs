@@ -0,0 +1,115 @@
+use crate::app::App;
+use crate::report::{self, format_duration};
+use std::error::Error;
+
+const USAGE: &str = "\
+usage: todo                            open the task list
+       todo list [status]              list tasks, optionally only those with a status
+                                       (a state name, open or closed)
+       todo add <description>          add a task
+       todo done <id>                  mark a task done
+       todo status <id> <state> [reason]
+                                       set a task's status; waiting takes a reason
+       todo report [tag|project] [<from> [to <to>]]
+                                       total the time tracked by tag or project,
+                                       over the last 7 days unless given dates";
+
+/// Runs one command given on the command line against tasks.json, without
+/// opening the terminal UI.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut app = App::new();
+    if let Some(warning) = app.status_message.take() {
+        eprintln!("{}", warning);
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["list"] => return list(&app, None),
+        ["list", status] => return list(&app, Some(status)),
+        ["report", args @ ..] => return print_report(&app, &args.join(" ")),
+        ["add", description @ ..] if !description.is_empty() => {
+            app.input = description.join(" ");
+            app.add_task();
+        }
+        ["done", id] => set_status(&mut app, id, "done", "")?,
+        ["status", id, status, reason @ ..] => set_status(&mut app, id, status, &reason.join(" "))?,
+        ["help" | "-h" | "--help"] => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        _ => return Err(USAGE.into()),
+    }
+    app.save();
+    Ok(())
+}
+
+/// Prints the task tree in the saved sort order, or only the tasks with
+/// `status` if given.
+fn list(app: &App, status: Option<&str>) -> Result<(), Box<dyn Error>> {
+    if let Some(status) = status {
+        if !["open", "closed"].contains(&status) && app.find_status(status).is_none() {
+            return Err(format!("Unknown status: {}", status).into());
+        }
+    }
+    for row in app.visible_rows() {
+        let Some(task) = app.row_task(&row) else {
+            continue;
+        };
+        if status.is_some_and(|status| !task.has_status(status)) {
+            continue;
+        }
+        let indent = match status {
+            Some(_) => String::new(),
+            None => "  ".repeat(row.depth()),
+        };
+        let mut line = format!(
+            "{}{:>3} {} {}",
+            indent,
+            task.id,
+            app.status_symbol(&task.status),
+            task.description
+        );
+        if let Some(due_date) = &task.due_date {
+            line.push_str(&format!(" (due: {})", due_date));
+        }
+        let blockers: Vec<String> = app.blockers(task).iter().map(|b| format!("#{}", b.id)).collect();
+        if !blockers.is_empty() {
+            line.push_str(&format!(" (blocked by {})", blockers.join(", ")));
+        }
+        if let Some(reason) = &task.waiting_on {
+            line.push_str(&format!(" (waiting on {})", reason));
+        }
+        if !task.time_entries.is_empty() {
+            let running = if task.is_timing() { ", running" } else { "" };
+            line.push_str(&format!(" ({}{})", format_duration(task.time_spent()), running));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn print_report(app: &App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (grouping, from, to) = report::parse_args(args)?;
+    let report = report::report(app, grouping, from, to);
+    println!("Time by {}, {} to {}", report.grouping.name(), report.from, report.to);
+    let width = report.rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0).max(5);
+    for (name, time) in &report.rows {
+        println!("  {:<width$}  {:>8}", name, format_duration(*time), width = width);
+    }
+    println!("  {:<width$}  {:>8}", "Total", format_duration(report.total), width = width);
+    Ok(())
+}
+
+fn set_status(app: &mut App, id: &str, status: &str, reason: &str) -> Result<(), Box<dyn Error>> {
+    let id: usize = id.parse().map_err(|_| format!("Not a task ID: {}", id))?;
+    if !app.select_id(id) {
+        return Err(format!("No task with ID {}", id).into());
+    }
+    if app.find_status(status).is_none() {
+        return Err(format!("Unknown status: {}", status).into());
+    }
+    app.set_status(status, reason);
+    if let Some(message) = app.status_message.take() {
+        println!("{}", message);
+    }
+    Ok(())
+}
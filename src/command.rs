@@ -0,0 +1,373 @@
+use crate::app::{App, View};
+use crate::board::Grouping;
+use crate::keymap::Action;
+use crate::sort::SortMode;
+use crate::task::Task;
+use std::fs;
+use std::path::Path;
+
+/// A command typed on the `:` command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Any action, by the name it has in the keymap.
+    Action(Action),
+    Sort(SortMode),
+    /// Tag edits as typed in the tags popup, e.g. `+work -home`.
+    Tag(String),
+    Due(String),
+    ArchiveDone,
+    Export(ExportFormat, String),
+    Theme(String),
+    View(View),
+    SaveView(String),
+    ApplyView(String),
+    DeleteView(String),
+    /// Shows a project by name, or `all` or `inbox`.
+    Project(String),
+    AddProject(String),
+    RenameProject(String),
+    ProjectColor(String),
+    ProjectParent(String),
+    DeleteProject,
+    /// Moves the target tasks to a project by name, or to `inbox`.
+    Move(String),
+    /// Sets the status of the target tasks, with what a waiting task is
+    /// waiting on.
+    Status(String, String),
+    /// Dependency edits for the target tasks, e.g. `3 -4` or `none`.
+    Depends(String),
+    /// IDs of tasks the target tasks should block.
+    Blocks(String),
+    /// Sums tracked time, see `report::parse_args` for the arguments.
+    Report(String),
+    /// Shows the board with its columns grouped this way.
+    Board(Grouping),
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+/// Commands beyond the action names.
+const COMMANDS: [&str; 14] = [
+    "sort", "tag", "due", "archive", "export", "theme", "view", "project", "move", "board",
+    "blocks", "report", "w", "wq",
+];
+
+const USAGE_SORT: &str = "usage: sort manual|priority|due|created|alpha|smart";
+const USAGE_EXPORT: &str = "usage: export md|json <file>";
+const USAGE_PROJECT: &str =
+    "usage: project all|inbox|<name>|add <name>|rename <name>|color <color>|parent <name>|delete";
+const USAGE_VIEW: &str =
+    "usage: view tasks|board|next|agenda|stats|archive|<saved view>|save <name>|delete <name>";
+const USAGE_BOARD: &str = "usage: board status|priority|tag";
+
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+    let (name, args) = match input.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (input, ""),
+    };
+    let command = match (name, args) {
+        ("", _) => return Err("Empty command".to_string()),
+        // Without arguments these fall through to their actions
+        ("sort", args) if !args.is_empty() => {
+            Command::Sort(SortMode::from_name(args).ok_or(USAGE_SORT)?)
+        }
+        ("tag" | "tags", args) if !args.is_empty() => Command::Tag(args.to_string()),
+        ("tag", _) => Command::Action(Action::Tags),
+        ("due", args) if !args.is_empty() => Command::Due(args.to_string()),
+        ("theme", args) if !args.is_empty() => Command::Theme(args.to_string()),
+        ("archive", "done") => Command::ArchiveDone,
+        ("archive", _) => return Err("usage: archive done".to_string()),
+        ("export", args) => {
+            let (format, file) = args.split_once(char::is_whitespace).ok_or(USAGE_EXPORT)?;
+            let format = match format {
+                "md" | "markdown" => ExportFormat::Markdown,
+                "json" => ExportFormat::Json,
+                _ => return Err(USAGE_EXPORT.to_string()),
+            };
+            Command::Export(format, file.trim().to_string())
+        }
+        ("view", "tasks") => Command::View(View::Tasks),
+        ("view", "board") => Command::View(View::Board),
+        ("view", "next") => Command::View(View::Next),
+        ("view", "agenda") => Command::View(View::Agenda),
+        ("view", "stats") => Command::View(View::Stats),
+        ("view", "archive") => Command::View(View::Archive),
+        ("view", "") => return Err(USAGE_VIEW.to_string()),
+        ("view", args) => match args.split_once(char::is_whitespace) {
+            Some(("save", name)) => Command::SaveView(name.trim().to_string()),
+            Some(("delete", name)) => Command::DeleteView(name.trim().to_string()),
+            _ => Command::ApplyView(args.to_string()),
+        },
+        ("project", "") => return Err(USAGE_PROJECT.to_string()),
+        ("project", "delete") => Command::DeleteProject,
+        ("project", args) => match args.split_once(char::is_whitespace) {
+            Some(("add", name)) => Command::AddProject(name.trim().to_string()),
+            Some(("rename", name)) => Command::RenameProject(name.trim().to_string()),
+            Some(("color", color)) => Command::ProjectColor(color.trim().to_string()),
+            Some(("parent", name)) => Command::ProjectParent(name.trim().to_string()),
+            _ => Command::Project(args.to_string()),
+        },
+        ("move", args) if !args.is_empty() => Command::Move(args.to_string()),
+        ("status", args) if !args.is_empty() => match args.split_once(char::is_whitespace) {
+            Some((name, reason)) => Command::Status(name.to_string(), reason.trim().to_string()),
+            None => Command::Status(args.to_string(), String::new()),
+        },
+        ("depends", args) if !args.is_empty() => Command::Depends(args.to_string()),
+        ("blocks", args) if !args.is_empty() => Command::Blocks(args.to_string()),
+        ("blocks", _) => return Err("usage: blocks <id>...".to_string()),
+        ("report", args) => Command::Report(args.to_string()),
+        ("board", "") => Command::View(View::Board),
+        ("board", args) => Command::Board(Grouping::from_name(args).ok_or(USAGE_BOARD)?),
+        ("w" | "write", "") => Command::Write,
+        // Quitting always saves
+        ("q" | "wq" | "x", "") => Command::Action(Action::Quit),
+        (name, "") => Command::Action(
+            Action::from_name(name).ok_or_else(|| format!("Unknown command: {}", name))?,
+        ),
+        (name, _) if Action::from_name(name).is_some() => {
+            return Err(format!("{} takes no arguments", name))
+        }
+        (name, _) => return Err(format!("Unknown command: {}", name)),
+    };
+    Ok(command)
+}
+
+/// Completes the last word of `input`. Returns the new input and, when
+/// more than one candidate matches, every candidate.
+pub fn complete(input: &str, app: &App) -> (String, Vec<String>) {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let (done, current) = if input.is_empty() || input.ends_with(char::is_whitespace) {
+        (&words[..], "")
+    } else {
+        (&words[..words.len() - 1], words[words.len() - 1])
+    };
+
+    let candidates: Vec<String> = candidates(done, current, app)
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect();
+
+    let prefix = &input[..input.len() - current.len()];
+    match candidates.as_slice() {
+        [] => (input.to_string(), Vec::new()),
+        [only] if only.ends_with('/') => (format!("{}{}", prefix, only), Vec::new()),
+        [only] => (format!("{}{} ", prefix, only), Vec::new()),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.as_str(), |common, candidate| {
+                let len = common
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, a), _)| i + a.len_utf8());
+                &common[..len]
+            });
+            (format!("{}{}", prefix, common), candidates.clone())
+        }
+    }
+}
+
+fn candidates(done: &[&str], current: &str, app: &App) -> Vec<String> {
+    let strings = |words: &[&str]| words.iter().map(|w| w.to_string()).collect();
+    match done {
+        [] => {
+            let mut names: Vec<String> = strings(&COMMANDS);
+            names.extend(Action::ALL.iter().map(|a| a.name().to_string()));
+            names.sort();
+            names.dedup();
+            names
+        }
+        ["sort"] => SortMode::ALL.iter().map(|m| m.name().to_string()).collect(),
+        ["tag" | "tags", ..] => {
+            let sign = match current.chars().next() {
+                Some(c @ ('+' | '-')) => c.to_string(),
+                _ => "+".to_string(),
+            };
+            let mut tags = Vec::new();
+            collect_tags(&app.tasks, &mut tags);
+            tags.sort();
+            tags.dedup();
+            tags.into_iter().map(|tag| format!("{}{}", sign, tag)).collect()
+        }
+        ["due"] => strings(&[
+            "today", "tomorrow", "monday", "tuesday", "wednesday", "thursday", "friday",
+            "saturday", "sunday",
+        ]),
+        ["archive"] => strings(&["done"]),
+        ["report"] => strings(&["tag", "project"]),
+        ["export"] => strings(&["md", "json"]),
+        ["export", _] => file_candidates(current),
+        ["theme"] => app.themes.iter().map(|theme| theme.name.clone()).collect(),
+        ["view"] => {
+            let mut names: Vec<String> = strings(&["tasks", "board", "next", "agenda", "stats", "archive", "save", "delete"]);
+            names.extend(app.views.iter().map(|view| view.name.clone()));
+            names
+        }
+        ["view", "delete"] => app.views.iter().map(|view| view.name.clone()).collect(),
+        ["project"] => {
+            let mut names: Vec<String> =
+                strings(&["all", "inbox", "add", "rename", "color", "parent", "delete"]);
+            names.extend(project_names(app));
+            names
+        }
+        ["project", "parent"] => {
+            let mut names = vec!["none".to_string()];
+            names.extend(project_names(app));
+            names
+        }
+        ["project", "color"] => strings(&[
+            "none", "red", "green", "yellow", "blue", "magenta", "cyan", "gray",
+        ]),
+        ["status"] => app.statuses().iter().map(|s| s.name().to_string()).collect(),
+        ["board"] => Grouping::ALL.iter().map(|g| g.name().to_string()).collect(),
+        ["move"] => {
+            let mut names = vec!["inbox".to_string()];
+            names.extend(project_names(app));
+            names
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn project_names(app: &App) -> Vec<String> {
+    app.project_tree()
+        .into_iter()
+        .map(|(_, project)| project.name.clone())
+        .collect()
+}
+
+/// Tag names without their `#`, from the whole task tree.
+fn collect_tags(tasks: &[Task], tags: &mut Vec<String>) {
+    for task in tasks {
+        tags.extend(task.tags.iter().map(|tag| tag.trim_start_matches('#').to_string()));
+        collect_tags(&task.sub_tasks, tags);
+    }
+}
+
+/// Paths that start with `current`, with directories ending in `/`.
+fn file_candidates(current: &str) -> Vec<String> {
+    let (dir, dir_prefix) = match current.rfind('/') {
+        Some(i) => (&current[..=i], &current[..=i]),
+        None => (".", ""),
+    };
+    let Ok(entries) = fs::read_dir(Path::new(dir)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            format!("{}{}{}", dir_prefix, name, slash)
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Project;
+    use serde_json::json;
+
+    fn app() -> App {
+        let task = serde_json::from_value(json!({
+            "id": 1,
+            "description": "Write report #work",
+            "priority": "Medium",
+            "due_date": null,
+            "sub_tasks": [],
+            "tags": ["#work", "#writing"],
+        }))
+        .unwrap();
+        let projects = ["Home", "Hobbies"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Project {
+                id: i + 1,
+                name: name.to_string(),
+                color: None,
+                parent: None,
+            })
+            .collect();
+        App::with_tasks(vec![task], projects)
+    }
+
+    #[test]
+    fn commands_parse_with_their_arguments() {
+        assert_eq!(parse(" sort due "), Ok(Command::Sort(SortMode::DueDate)));
+        assert_eq!(parse("tag +work -home"), Ok(Command::Tag("+work -home".to_string())));
+        assert_eq!(
+            parse("export md my tasks.md"),
+            Ok(Command::Export(ExportFormat::Markdown, "my tasks.md".to_string()))
+        );
+        assert_eq!(parse("view agenda"), Ok(Command::View(View::Agenda)));
+        assert_eq!(parse("view save Work week"), Ok(Command::SaveView("Work week".to_string())));
+        assert_eq!(parse("project add Side jobs"), Ok(Command::AddProject("Side jobs".to_string())));
+        assert_eq!(parse("project delete"), Ok(Command::DeleteProject));
+        assert_eq!(
+            parse("status waiting reply from Sam"),
+            Ok(Command::Status("waiting".to_string(), "reply from Sam".to_string()))
+        );
+        assert_eq!(parse("board tag"), Ok(Command::Board(Grouping::Tag)));
+        assert_eq!(parse("w"), Ok(Command::Write));
+    }
+
+    #[test]
+    fn bare_names_fall_through_to_actions() {
+        assert_eq!(parse("undo"), Ok(Command::Action(Action::Undo)));
+        assert_eq!(parse("tag"), Ok(Command::Action(Action::Tags)));
+        assert_eq!(parse("wq"), Ok(Command::Action(Action::Quit)));
+    }
+
+    #[test]
+    fn unknown_commands_and_bad_arguments_are_refused() {
+        assert_eq!(parse(""), Err("Empty command".to_string()));
+        assert_eq!(parse("frobnicate"), Err("Unknown command: frobnicate".to_string()));
+        assert_eq!(parse("frobnicate now"), Err("Unknown command: frobnicate".to_string()));
+        assert_eq!(parse("undo twice"), Err("undo takes no arguments".to_string()));
+        assert_eq!(parse("sort sideways"), Err(USAGE_SORT.to_string()));
+        assert_eq!(parse("export pdf out.pdf"), Err(USAGE_EXPORT.to_string()));
+        assert_eq!(parse("export md"), Err(USAGE_EXPORT.to_string()));
+        assert_eq!(parse("board columns"), Err(USAGE_BOARD.to_string()));
+    }
+
+    #[test]
+    fn completion_fills_in_a_single_match() {
+        let app = app();
+        assert_eq!(complete("so", &app), ("sort ".to_string(), Vec::new()));
+        assert_eq!(complete("sort pr", &app), ("sort priority ".to_string(), Vec::new()));
+        assert_eq!(complete("view ag", &app), ("view agenda ".to_string(), Vec::new()));
+        assert_eq!(complete("xyz", &app), ("xyz".to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn completion_lists_every_match_and_their_common_prefix() {
+        let app = app();
+        let (input, candidates) = complete("project Ho", &app);
+        assert_eq!(input, "project Ho");
+        assert_eq!(candidates, ["Home", "Hobbies"]);
+        let (input, candidates) = complete("tag -w", &app);
+        assert_eq!(input, "tag -w");
+        assert_eq!(candidates, ["-work", "-writing"]);
+        let (input, candidates) = complete("tag +work +wr", &app);
+        assert_eq!(input, "tag +work +writing ");
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn completion_offers_arguments_after_a_space() {
+        let app = app();
+        let (_, candidates) = complete("board ", &app);
+        assert_eq!(candidates, ["status", "priority", "tag"]);
+        let (_, candidates) = complete("move ", &app);
+        assert_eq!(candidates, ["inbox", "Home", "Hobbies"]);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::keymap::KeysConfig;
+use crate::task::Status;
+use crate::theme::ColorMode;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// User configuration read from config.toml. Every field is optional.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Completed tasks older than this many days move to the archive on
+    /// startup. Unset disables automatic archiving.
+    pub archive_after_days: Option<u32>,
+    pub confirm: ConfirmConfig,
+    /// Theme used until one is picked at runtime.
+    pub theme: Option<String>,
+    pub color_mode: ColorMode,
+    pub keys: KeysConfig,
+    /// Workflow states beyond the built-in ones.
+    pub states: Vec<StateConfig>,
+    pub pomodoro: PomodoroConfig,
+}
+
+/// Lengths of the pomodoro timer's phases, in minutes.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// Every this many work phases, the break is a long one. 0 turns long
+    /// breaks off.
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        PomodoroConfig {
+            work_minutes: 25,
+            break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroConfig {
+    /// Raises any phase length of 0 to 1 minute, as a 0 would end the phase
+    /// on every tick, returning an error for each.
+    pub fn clamp(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (name, minutes) in [
+            ("work_minutes", &mut self.work_minutes),
+            ("break_minutes", &mut self.break_minutes),
+            ("long_break_minutes", &mut self.long_break_minutes),
+        ] {
+            if *minutes == 0 {
+                *minutes = 1;
+                errors.push(format!("pomodoro.{}: must be at least 1", name));
+            }
+        }
+        errors
+    }
+}
+
+/// A custom workflow state. Custom states count as open, like `doing`, and
+/// come between `waiting` and `done` on the board.
+#[derive(Deserialize, Debug, Clone)]
+pub struct StateConfig {
+    pub name: String,
+    #[serde(default = "default_state_symbol")]
+    pub symbol: String,
+}
+
+fn default_state_symbol() -> String {
+    Status::Custom(String::new()).symbol().to_string()
+}
+
+/// Which destructive actions ask for confirmation first. All of them do
+/// unless turned off.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfirmConfig {
+    pub delete: bool,
+    pub bulk_delete: bool,
+    pub archive_all: bool,
+    pub purge: bool,
+    pub delete_project: bool,
+}
+
+impl Default for ConfirmConfig {
+    fn default() -> Self {
+        ConfirmConfig {
+            delete: true,
+            bulk_delete: true,
+            archive_all: true,
+            purge: true,
+            delete_project: true,
+        }
+    }
+}
+
+pub fn load_config<P: AsRef<Path>>(path: P) -> io::Result<Config> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(e),
+    };
+    toml::from_str(&text).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_pomodoro_lengths_are_raised_to_one() {
+        let mut config: Config = toml::from_str("[pomodoro]\nwork_minutes = 0\nlong_break_minutes = 0").unwrap();
+        let errors = config.pomodoro.clamp();
+        assert_eq!(
+            errors,
+            ["pomodoro.work_minutes: must be at least 1", "pomodoro.long_break_minutes: must be at least 1"]
+        );
+        assert_eq!(config.pomodoro.work_minutes, 1);
+        assert_eq!(config.pomodoro.break_minutes, 5);
+        assert_eq!(config.pomodoro.long_break_minutes, 1);
+    }
+}
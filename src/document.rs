@@ -0,0 +1,257 @@
+use crate::task::{Priority, Task};
+
+const HEADER: &str = "\
+# One task per line. Indent by two spaces to make a subtask.
+# [x] marks a task done. Priority: !high !low (default medium), due date: due:2025-01-31
+# or due:\"next friday\", tags: #tag. Keep the <!-- id:N --> markers so edits match
+# existing tasks: lines without one are added, and tasks whose line is removed are deleted.
+# Lines that are not list items are ignored.
+
+";
+
+/// A task line read back from an edited document.
+#[derive(Debug)]
+pub struct ParsedTask {
+    pub id: Option<usize>,
+    pub completed: bool,
+    pub description: String,
+    pub priority: Priority,
+    pub due_date: Option<String>,
+    pub tags: Vec<String>,
+    pub sub_tasks: Vec<ParsedTask>,
+    /// The line as written, without indentation, to tell whether it was
+    /// edited.
+    pub line: String,
+}
+
+/// Writes tasks as an indented markdown checklist.
+pub fn render_document<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let mut text = HEADER.to_string();
+    for task in tasks {
+        render_task(task, 0, &mut text);
+    }
+    text
+}
+
+fn render_task(task: &Task, depth: usize, text: &mut String) {
+    text.push_str(&"  ".repeat(depth));
+    text.push_str(&task_line(task));
+    text.push('\n');
+    for sub_task in task.sub_tasks.iter() {
+        render_task(sub_task, depth + 1, text);
+    }
+}
+
+/// Writes tasks as a plain markdown checklist for sharing: no header and
+/// no ID markers.
+pub fn export_markdown<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let mut text = String::new();
+    for task in tasks {
+        export_task(task, 0, &mut text);
+    }
+    text
+}
+
+fn export_task(task: &Task, depth: usize, text: &mut String) {
+    text.push_str(&"  ".repeat(depth));
+    text.push_str(&checklist_item(task));
+    text.push('\n');
+    for sub_task in task.sub_tasks.iter() {
+        export_task(sub_task, depth + 1, text);
+    }
+}
+
+/// A task's line in the document, without indentation.
+pub fn task_line(task: &Task) -> String {
+    format!("{} <!-- id:{} -->", checklist_item(task), task.id)
+}
+
+fn checklist_item(task: &Task) -> String {
+    let mut line = format!(
+        "- [{}] {}",
+        if task.is_closed() { 'x' } else { ' ' },
+        task.description
+    );
+    match task.priority {
+        Priority::High => line.push_str(" !high"),
+        Priority::Medium => {}
+        Priority::Low => line.push_str(" !low"),
+    }
+    if let Some(due_date) = &task.due_date {
+        line.push_str(" due:");
+        line.push_str(&quote_due(due_date));
+    }
+    // Tags typed with the task are already part of its description
+    let words: Vec<&str> = task.description.split_whitespace().collect();
+    for tag in task.tags.iter().filter(|tag| !words.contains(&tag.as_str())) {
+        line.push(' ');
+        line.push_str(tag);
+    }
+    line
+}
+
+/// Reads an edited document back into a tree of tasks.
+pub fn parse_document(text: &str) -> Vec<ParsedTask> {
+    let mut roots: Vec<ParsedTask> = Vec::new();
+    // Indentation of each open level of nesting
+    let mut stack: Vec<usize> = Vec::new();
+    for raw in text.lines() {
+        let content = raw.trim_start();
+        if !(content.starts_with("- ") || content.starts_with("* ")) {
+            continue;
+        }
+        let task = parse_line(content.trim_end());
+        if task.id.is_none() && task.description.is_empty() {
+            continue;
+        }
+
+        let indent = raw[..raw.len() - content.len()].replace('\t', "  ").len();
+        while stack.last().is_some_and(|&open| open >= indent) {
+            stack.pop();
+        }
+        let mut siblings = &mut roots;
+        for _ in 0..stack.len() {
+            siblings = &mut siblings.last_mut().unwrap().sub_tasks;
+        }
+        siblings.push(task);
+        stack.push(indent);
+    }
+    roots
+}
+
+fn parse_line(line: &str) -> ParsedTask {
+    let mut rest = line.get(2..).unwrap_or("").trim_start();
+    let mut completed = false;
+    if let Some(after) = rest.strip_prefix("[ ]") {
+        rest = after;
+    } else if let Some(after) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        completed = true;
+        rest = after;
+    }
+
+    let mut id = None;
+    if let Some(start) = rest.rfind("<!--") {
+        let marker = rest[start + 4..].trim_end().trim_end_matches("-->").trim();
+        if let Some(n) = marker.strip_prefix("id:").and_then(|n| n.trim().parse().ok()) {
+            id = Some(n);
+            rest = &rest[..start];
+        }
+    }
+
+    let mut due_date = None;
+    let unquoted;
+    if let Some((date, remaining)) = take_quoted_due(rest) {
+        due_date = Some(date);
+        unquoted = remaining;
+        rest = &unquoted;
+    }
+
+    let mut priority = Priority::Medium;
+    let mut words = Vec::new();
+    for word in rest.split_whitespace() {
+        match word {
+            "!high" | "!h" => priority = Priority::High,
+            "!medium" | "!med" | "!m" => priority = Priority::Medium,
+            "!low" | "!l" => priority = Priority::Low,
+            _ => match word.strip_prefix("due:") {
+                Some(date) if !date.is_empty() => due_date = Some(date.to_string()),
+                _ => words.push(word),
+            },
+        }
+    }
+
+    // As when adding a task, tags stay part of the description
+    let tags = words
+        .iter()
+        .filter(|word| word.starts_with('#'))
+        .map(|word| word.to_string())
+        .collect();
+
+    ParsedTask {
+        id,
+        completed,
+        description: words.join(" "),
+        priority,
+        due_date,
+        tags,
+        sub_tasks: Vec::new(),
+        line: line.to_string(),
+    }
+}
+
+/// Due dates typed by hand may contain spaces, so those are written in
+/// quotes, with `"` and `\\` escaped by a backslash.
+fn quote_due(due_date: &str) -> String {
+    if !due_date.is_empty() && !due_date.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return due_date.to_string();
+    }
+    let mut quoted = String::from('"');
+    for c in due_date.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Takes the first quoted `due:"..."` out of `text`, returning its value
+/// and the rest of the text.
+fn take_quoted_due(text: &str) -> Option<(String, String)> {
+    let start = text
+        .match_indices("due:\"")
+        .map(|(i, _)| i)
+        .find(|&i| text[..i].chars().next_back().is_none_or(char::is_whitespace))?;
+    let mut date = String::new();
+    let mut chars = text[start + 5..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => date.extend(chars.next().map(|(_, c)| c)),
+            '"' => {
+                let end = start + 5 + i + 1;
+                return Some((date, format!("{} {}", &text[..start], &text[end..])));
+            }
+            _ => date.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_one(line: &str) -> ParsedTask {
+        parse_document(line).remove(0)
+    }
+
+    #[test]
+    fn due_dates_survive_a_round_trip() {
+        for due in ["2025-01-31", "next friday", "end_of_month", "after \"the\" launch", "a\\b", ""] {
+            let task = parse_one(&format!("- [ ] Call Sam due:{} <!-- id:1 -->", quote_due(due)));
+            assert_eq!(task.due_date.as_deref(), Some(due));
+            assert_eq!(task.description, "Call Sam");
+        }
+    }
+
+    #[test]
+    fn words_after_a_quoted_due_date_are_kept() {
+        let task = parse_one("- [x] Ship it due:\"next week\" !high #work <!-- id:4 -->");
+        assert_eq!(task.id, Some(4));
+        assert!(task.completed);
+        assert_eq!(task.due_date.as_deref(), Some("next week"));
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.description, "Ship it #work");
+        assert_eq!(task.tags, ["#work"]);
+    }
+
+    #[test]
+    fn indentation_nests_tasks() {
+        let parsed = parse_document("- [ ] One\n  - [ ] Two\n    - [ ] Three\n- [ ] Four\nnot a task\n");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].sub_tasks[0].description, "Two");
+        assert_eq!(parsed[0].sub_tasks[0].sub_tasks[0].description, "Three");
+        assert_eq!(parsed[1].description, "Four");
+    }
+}
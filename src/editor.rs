@@ -0,0 +1,116 @@
+use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::Backend, Terminal};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Opens `text` in the user's editor and returns the edited text once it
+/// exits. The terminal is handed over to the editor for the duration and
+/// restored afterwards, even if the editor fails to start.
+pub fn edit_in_editor<B: Backend>(
+    terminal: &mut Terminal<B>,
+    text: &str,
+    extension: &str,
+) -> io::Result<String> {
+    let (path, mut file) = create_temp_file(extension)?;
+    if let Err(e) = file.write_all(text.as_bytes()) {
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    drop(file);
+
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        DisableFocusChange
+    )?;
+
+    let status = editor_command().arg(&path).status();
+
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste,
+        EnableFocusChange
+    )?;
+    terminal.clear()?;
+
+    let result = match status {
+        Ok(status) if status.success() => fs::read_to_string(&path),
+        Ok(status) => Err(io::Error::other(format!("editor exited with {}", status))),
+        Err(e) => Err(e),
+    };
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Creates a file for the editor in the shared temp directory. The name
+/// must not exist yet, so a file or link planted there by someone else is
+/// never written through, and only the owner can read the file.
+fn create_temp_file(extension: &str) -> io::Result<(PathBuf, File)> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    for attempt in 0..100 {
+        let name = format!("todo-{}-{}-{}.{}", std::process::id(), nanos, attempt, extension);
+        let path = env::temp_dir().join(name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "no free temporary file name"))
+}
+
+/// `$VISUAL`, then `$EDITOR`, then a platform default. The variable may
+/// include arguments, e.g. `code --wait`.
+fn editor_command() -> Command {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut parts = editor.split_whitespace();
+    let mut command = Command::new(parts.next().unwrap());
+    command.args(parts);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_files_are_new_and_private() {
+        let (first, _) = create_temp_file("md").unwrap();
+        let (second, _) = create_temp_file("md").unwrap();
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+use crossterm::event::{self, Event, KeyEvent, KeyEventKind, MouseEvent};
+use std::io;
+use std::time::Duration;
+
+/// The terminal events the app reacts to.
+#[derive(Debug)]
+pub enum AppEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// Text pasted while bracketed paste is on, delivered in one piece
+    /// instead of as a key per character.
+    Paste(String),
+    Resize(u16, u16),
+    FocusGained,
+    FocusLost,
+}
+
+/// Waits up to `timeout` for the next event the app cares about, returning
+/// `None` if none came. Only key presses come through: terminals that also
+/// report releases and repeats, such as the Windows console, would
+/// otherwise trigger every action twice.
+pub fn next_event(timeout: Duration) -> io::Result<Option<AppEvent>> {
+    loop {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        let event = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => AppEvent::Key(key),
+            Event::Key(_) => continue,
+            Event::Mouse(mouse) => AppEvent::Mouse(mouse),
+            Event::Paste(text) => AppEvent::Paste(text),
+            Event::Resize(width, height) => AppEvent::Resize(width, height),
+            Event::FocusGained => AppEvent::FocusGained,
+            Event::FocusLost => AppEvent::FocusLost,
+        };
+        return Ok(Some(event));
+    }
+}
@@ -0,0 +1,1043 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Everything a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    MoveDown,
+    MoveUp,
+    Indent,
+    Outdent,
+    ToggleDone,
+    Add,
+    AddSubtask,
+    Delete,
+    Priority,
+    DueDate,
+    Tags,
+    Search,
+    Sort,
+    Details,
+    Notes,
+    NotesInEditor,
+    EditDocument,
+    ArchiveDone,
+    Restore,
+    Purge,
+    SwitchView,
+    Select,
+    Mark,
+    MarkAll,
+    Undo,
+    Theme,
+    Focus,
+    FocusTask,
+    ZoomIn,
+    ZoomOut,
+    /// Confirms the open input or prompt.
+    Submit,
+    /// Closes the open input or prompt, or leaves select mode.
+    Cancel,
+    NewLine,
+    SearchArchive,
+    Help,
+    Command,
+    Complete,
+    Picker,
+    Sidebar,
+    NextProject,
+    PreviousProject,
+    MoveToProject,
+    Left,
+    Right,
+    MoveLeft,
+    MoveRight,
+    Group,
+    Status,
+    Depends,
+    PreviousDay,
+    NextDay,
+    PreviousMonth,
+    NextMonth,
+    Today,
+    Timer,
+    Pomodoro,
+}
+
+impl Action {
+    pub const ALL: [Action; 61] = [
+        Action::Quit,
+        Action::Down,
+        Action::Up,
+        Action::Top,
+        Action::Bottom,
+        Action::MoveDown,
+        Action::MoveUp,
+        Action::Indent,
+        Action::Outdent,
+        Action::ToggleDone,
+        Action::Add,
+        Action::AddSubtask,
+        Action::Delete,
+        Action::Priority,
+        Action::DueDate,
+        Action::Tags,
+        Action::Search,
+        Action::Sort,
+        Action::Details,
+        Action::Notes,
+        Action::NotesInEditor,
+        Action::EditDocument,
+        Action::ArchiveDone,
+        Action::Restore,
+        Action::Purge,
+        Action::SwitchView,
+        Action::Select,
+        Action::Mark,
+        Action::MarkAll,
+        Action::Undo,
+        Action::Theme,
+        Action::Focus,
+        Action::FocusTask,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::Submit,
+        Action::Cancel,
+        Action::NewLine,
+        Action::SearchArchive,
+        Action::Help,
+        Action::Command,
+        Action::Complete,
+        Action::Picker,
+        Action::Sidebar,
+        Action::NextProject,
+        Action::PreviousProject,
+        Action::MoveToProject,
+        Action::Left,
+        Action::Right,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Group,
+        Action::Status,
+        Action::Depends,
+        Action::PreviousDay,
+        Action::NextDay,
+        Action::PreviousMonth,
+        Action::NextMonth,
+        Action::Today,
+        Action::Timer,
+        Action::Pomodoro,
+    ];
+
+    /// The name used in the config file and shown in the footer.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::MoveDown => "move-down",
+            Action::MoveUp => "move-up",
+            Action::Indent => "indent",
+            Action::Outdent => "outdent",
+            Action::ToggleDone => "done",
+            Action::Add => "add",
+            Action::AddSubtask => "subtask",
+            Action::Delete => "delete",
+            Action::Priority => "priority",
+            Action::DueDate => "due",
+            Action::Tags => "tags",
+            Action::Search => "search",
+            Action::Sort => "sort",
+            Action::Details => "details",
+            Action::Notes => "notes",
+            Action::NotesInEditor => "notes-editor",
+            Action::EditDocument => "edit-all",
+            Action::ArchiveDone => "archive-done",
+            Action::Restore => "restore",
+            Action::Purge => "purge",
+            Action::SwitchView => "switch-view",
+            Action::Select => "select",
+            Action::Mark => "mark",
+            Action::MarkAll => "mark-all",
+            Action::Undo => "undo",
+            Action::Theme => "theme",
+            Action::Focus => "focus",
+            Action::FocusTask => "focus-task",
+            Action::ZoomIn => "zoom-in",
+            Action::ZoomOut => "zoom-out",
+            Action::Submit => "submit",
+            Action::Cancel => "cancel",
+            Action::NewLine => "newline",
+            Action::SearchArchive => "search-archive",
+            Action::Help => "help",
+            Action::Command => "command",
+            Action::Complete => "complete",
+            Action::Picker => "picker",
+            Action::Sidebar => "sidebar",
+            Action::NextProject => "next-project",
+            Action::PreviousProject => "previous-project",
+            Action::MoveToProject => "move",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::MoveLeft => "move-left",
+            Action::MoveRight => "move-right",
+            Action::Group => "group",
+            Action::Status => "status",
+            Action::Depends => "depends",
+            Action::PreviousDay => "previous-day",
+            Action::NextDay => "next-day",
+            Action::PreviousMonth => "previous-month",
+            Action::NextMonth => "next-month",
+            Action::Today => "today",
+            Action::Timer => "timer",
+            Action::Pomodoro => "pomodoro",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Save and quit",
+            Action::Down => "Select the next task, or scroll down",
+            Action::Up => "Select the previous task, or scroll up",
+            Action::Top => "Select the first task",
+            Action::Bottom => "Select the last task",
+            Action::MoveDown => "Move the task down among its siblings",
+            Action::MoveUp => "Move the task up among its siblings",
+            Action::Indent => "Make the task a subtask of the one above",
+            Action::Outdent => "Move the task out of its parent",
+            Action::ToggleDone => "Toggle done",
+            Action::Add => "Add a task",
+            Action::AddSubtask => "Add a subtask",
+            Action::Delete => "Delete",
+            Action::Priority => "Cycle priority",
+            Action::DueDate => "Set the due date",
+            Action::Tags => "Edit tags",
+            Action::Search => "Search",
+            Action::Sort => "Cycle the sort order",
+            Action::Details => "Toggle the details pane",
+            Action::Notes => "Edit notes",
+            Action::NotesInEditor => "Edit notes in $EDITOR",
+            Action::EditDocument => "Edit all tasks in $EDITOR",
+            Action::ArchiveDone => "Archive completed tasks",
+            Action::Restore => "Restore from the archive",
+            Action::Purge => "Purge the archive",
+            Action::SwitchView => "Cycle views (tasks, board, next, agenda, stats, archive)",
+            Action::Select => "Enter select mode",
+            Action::Mark => "Mark or unmark the task",
+            Action::MarkAll => "Mark every matching task",
+            Action::Undo => "Undo the last change",
+            Action::Theme => "Cycle the theme",
+            Action::Focus => "Toggle focus mode",
+            Action::FocusTask => "Focus on the current task",
+            Action::ZoomIn => "Shrink the margins",
+            Action::ZoomOut => "Grow the margins",
+            Action::Submit => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::NewLine => "Insert a line break",
+            Action::SearchArchive => "Include the archive in the search",
+            Action::Help => "Show every key",
+            Action::Command => "Type a command",
+            Action::Complete => "Complete the command or argument",
+            Action::Picker => "Find an action or saved view",
+            Action::Sidebar => "Toggle the project sidebar",
+            Action::NextProject => "Show the next project",
+            Action::PreviousProject => "Show the previous project",
+            Action::MoveToProject => "Move the task to a project",
+            Action::Left => "Select the column to the left",
+            Action::Right => "Select the column to the right",
+            Action::MoveLeft => "Move the card one column left",
+            Action::MoveRight => "Move the card one column right",
+            Action::Group => "Cycle the board's columns: status, priority, tag",
+            Action::Status => "Set the status, e.g. doing or waiting",
+            Action::Depends => "Set the tasks this one waits for, by ID",
+            Action::PreviousDay => "Show only the day before",
+            Action::NextDay => "Show only the day after",
+            Action::PreviousMonth => "Show the previous month",
+            Action::NextMonth => "Show the next month",
+            Action::Today => "Show only today",
+            Action::Timer => "Start or stop timing the task",
+            Action::Pomodoro => "Start or stop a pomodoro on the task",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// The set of bindings that applies, following the app's mode and view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyContext {
+    Tasks,
+    Board,
+    Agenda,
+    Stats,
+    Archive,
+    Select,
+    /// The single-line inputs: new task, due date and tags.
+    Input,
+    Search,
+    Notes,
+    Confirm,
+    Help,
+    Command,
+    Picker,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 13] = [
+        KeyContext::Tasks,
+        KeyContext::Board,
+        KeyContext::Agenda,
+        KeyContext::Stats,
+        KeyContext::Archive,
+        KeyContext::Select,
+        KeyContext::Input,
+        KeyContext::Search,
+        KeyContext::Notes,
+        KeyContext::Confirm,
+        KeyContext::Help,
+        KeyContext::Command,
+        KeyContext::Picker,
+    ];
+
+    /// The name of the context's table under `[keys]` in config.toml.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyContext::Tasks => "normal",
+            KeyContext::Board => "board",
+            KeyContext::Agenda => "agenda",
+            KeyContext::Stats => "stats",
+            KeyContext::Archive => "archive",
+            KeyContext::Select => "select",
+            KeyContext::Input => "input",
+            KeyContext::Search => "search",
+            KeyContext::Notes => "notes",
+            KeyContext::Confirm => "confirm",
+            KeyContext::Help => "help",
+            KeyContext::Command => "command",
+            KeyContext::Picker => "picker",
+        }
+    }
+
+    /// The heading for the context's section of the help screen.
+    pub fn title(self) -> &'static str {
+        match self {
+            KeyContext::Tasks => "Task list",
+            KeyContext::Board => "Board",
+            KeyContext::Agenda => "Agenda",
+            KeyContext::Stats => "Stats",
+            KeyContext::Archive => "Archive",
+            KeyContext::Select => "Select mode",
+            KeyContext::Input => "Adding a task, due date or tags",
+            KeyContext::Search => "Search",
+            KeyContext::Notes => "Notes",
+            KeyContext::Confirm => "Confirmation",
+            KeyContext::Help => "Help",
+            KeyContext::Command => "Command line",
+            KeyContext::Picker => "Action picker",
+        }
+    }
+
+    /// Whether keys without a binding are typed as text.
+    pub fn takes_text(self) -> bool {
+        matches!(
+            self,
+            KeyContext::Input
+                | KeyContext::Search
+                | KeyContext::Notes
+                | KeyContext::Command
+                | KeyContext::Picker
+        )
+    }
+}
+
+/// One key press: a key code plus modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Shift on a character is already part of the character, and
+    /// terminals disagree on whether to report it, so it is dropped.
+    fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Key {
+        let mut code = code;
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                code = KeyCode::Char(c.to_ascii_uppercase());
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+        }
+        Key {
+            code,
+            modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+
+    pub fn from_event(event: KeyEvent) -> Key {
+        Key::new(event.code, event.modifiers)
+    }
+
+    fn is_plain_char(self) -> bool {
+        matches!(self.code, KeyCode::Char(c) if c != ' ') && self.modifiers.is_empty()
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 16] = [
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("space", KeyCode::Char(' ')),
+];
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift-")?;
+        }
+        if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, code)| *code == self.code) {
+            return write!(f, "{}", name);
+        }
+        match self.code {
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Parses a key sequence such as `q`, `ctrl-s`, `shift-down`, `gg` or
+/// `ctrl-x ctrl-c`. Keys are separated by spaces; a run of plain
+/// characters like `gg` is a sequence of its own.
+pub fn parse_keys(text: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    for token in text.split_whitespace() {
+        let mut rest = token;
+        let mut modifiers = KeyModifiers::NONE;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let (modifier, len) = if lower.starts_with("ctrl-") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt-") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift-") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            // A lone `-` after a modifier is the minus key itself
+            if rest.len() == len {
+                break;
+            }
+            modifiers |= modifier;
+            rest = &rest[len..];
+        }
+
+        let lower = rest.to_ascii_lowercase();
+        let mut chars = rest.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            keys.push(Key::new(KeyCode::Char(c), modifiers));
+        } else if let Some((_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == lower) {
+            keys.push(Key::new(*code, modifiers));
+        } else if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            keys.push(Key::new(KeyCode::F(n), modifiers));
+        } else if modifiers.is_empty() {
+            keys.extend(rest.chars().map(|c| Key::new(KeyCode::Char(c), modifiers)));
+        } else {
+            return Err(format!("unknown key `{}`", token));
+        }
+    }
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+/// Writes a key sequence the way it would be typed in the config file.
+pub fn format_keys(keys: &[Key]) -> String {
+    if keys.iter().all(|key| key.is_plain_char()) {
+        keys.iter().map(|key| key.to_string()).collect()
+    } else {
+        keys.iter().map(|key| key.to_string()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub keys: Vec<Key>,
+    pub action: Action,
+}
+
+/// The built-in keymaps. `vim` and `emacs` add their navigation and
+/// editing keys on top of the default bindings.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Default,
+    Vim,
+    Emacs,
+}
+
+/// One key sequence or a list of them, as written in config.toml.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The `[keys]` table of config.toml: a preset, then per-context tables
+/// mapping action names to the keys that replace their preset bindings.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub preset: Preset,
+    #[serde(flatten)]
+    pub contexts: HashMap<String, HashMap<String, KeyList>>,
+}
+
+/// What a key press amounts to once bindings are looked up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolved {
+    Action(Action),
+    /// The key starts a longer sequence; wait for the next one.
+    Pending,
+    /// Nothing is bound, so input modes can type the key.
+    Unbound,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyContext, Vec<Binding>>,
+}
+
+const DEFAULT_BINDINGS: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::Tasks, "q", Action::Quit),
+    (KeyContext::Tasks, "down", Action::Down),
+    (KeyContext::Tasks, "up", Action::Up),
+    (KeyContext::Tasks, "home", Action::Top),
+    (KeyContext::Tasks, "end", Action::Bottom),
+    (KeyContext::Tasks, "shift-down", Action::MoveDown),
+    (KeyContext::Tasks, "J", Action::MoveDown),
+    (KeyContext::Tasks, "shift-up", Action::MoveUp),
+    (KeyContext::Tasks, "K", Action::MoveUp),
+    (KeyContext::Tasks, ">", Action::Indent),
+    (KeyContext::Tasks, "<", Action::Outdent),
+    (KeyContext::Tasks, "enter", Action::ToggleDone),
+    (KeyContext::Tasks, "a", Action::Add),
+    (KeyContext::Tasks, "s", Action::AddSubtask),
+    (KeyContext::Tasks, "d", Action::Delete),
+    (KeyContext::Tasks, "p", Action::Priority),
+    (KeyContext::Tasks, "D", Action::DueDate),
+    (KeyContext::Tasks, "t", Action::Tags),
+    (KeyContext::Tasks, "/", Action::Search),
+    (KeyContext::Tasks, "o", Action::Sort),
+    (KeyContext::Tasks, "i", Action::Details),
+    (KeyContext::Tasks, "n", Action::Notes),
+    (KeyContext::Tasks, "N", Action::NotesInEditor),
+    (KeyContext::Tasks, "E", Action::EditDocument),
+    (KeyContext::Tasks, "A", Action::ArchiveDone),
+    (KeyContext::Tasks, "v", Action::Select),
+    (KeyContext::Tasks, "u", Action::Undo),
+    (KeyContext::Tasks, "T", Action::Theme),
+    (KeyContext::Tasks, "f", Action::Focus),
+    (KeyContext::Tasks, "F", Action::FocusTask),
+    (KeyContext::Tasks, "tab", Action::SwitchView),
+    (KeyContext::Tasks, "+", Action::ZoomIn),
+    (KeyContext::Tasks, "-", Action::ZoomOut),
+    (KeyContext::Tasks, "?", Action::Help),
+    (KeyContext::Tasks, ":", Action::Command),
+    (KeyContext::Tasks, "ctrl-p", Action::Picker),
+    (KeyContext::Tasks, "b", Action::Sidebar),
+    (KeyContext::Tasks, "]", Action::NextProject),
+    (KeyContext::Tasks, "[", Action::PreviousProject),
+    (KeyContext::Tasks, "m", Action::MoveToProject),
+    (KeyContext::Tasks, "c", Action::Status),
+    (KeyContext::Tasks, "B", Action::Depends),
+    (KeyContext::Tasks, "w", Action::Timer),
+    (KeyContext::Tasks, "O", Action::Pomodoro),
+    (KeyContext::Board, "q", Action::Quit),
+    (KeyContext::Board, "down", Action::Down),
+    (KeyContext::Board, "up", Action::Up),
+    (KeyContext::Board, "left", Action::Left),
+    (KeyContext::Board, "right", Action::Right),
+    (KeyContext::Board, "h", Action::MoveLeft),
+    (KeyContext::Board, "l", Action::MoveRight),
+    (KeyContext::Board, "enter", Action::ToggleDone),
+    (KeyContext::Board, "a", Action::Add),
+    (KeyContext::Board, "d", Action::Delete),
+    (KeyContext::Board, "p", Action::Priority),
+    (KeyContext::Board, "D", Action::DueDate),
+    (KeyContext::Board, "t", Action::Tags),
+    (KeyContext::Board, "/", Action::Search),
+    (KeyContext::Board, "o", Action::Group),
+    (KeyContext::Board, "i", Action::Details),
+    (KeyContext::Board, "n", Action::Notes),
+    (KeyContext::Board, "u", Action::Undo),
+    (KeyContext::Board, "T", Action::Theme),
+    (KeyContext::Board, "tab", Action::SwitchView),
+    (KeyContext::Board, "+", Action::ZoomIn),
+    (KeyContext::Board, "-", Action::ZoomOut),
+    (KeyContext::Board, "?", Action::Help),
+    (KeyContext::Board, ":", Action::Command),
+    (KeyContext::Board, "ctrl-p", Action::Picker),
+    (KeyContext::Board, "b", Action::Sidebar),
+    (KeyContext::Board, "]", Action::NextProject),
+    (KeyContext::Board, "[", Action::PreviousProject),
+    (KeyContext::Board, "m", Action::MoveToProject),
+    (KeyContext::Board, "c", Action::Status),
+    (KeyContext::Board, "B", Action::Depends),
+    (KeyContext::Board, "w", Action::Timer),
+    (KeyContext::Board, "O", Action::Pomodoro),
+    (KeyContext::Agenda, "q", Action::Quit),
+    (KeyContext::Agenda, "down", Action::Down),
+    (KeyContext::Agenda, "up", Action::Up),
+    (KeyContext::Agenda, "home", Action::Top),
+    (KeyContext::Agenda, "end", Action::Bottom),
+    (KeyContext::Agenda, "left", Action::PreviousDay),
+    (KeyContext::Agenda, "right", Action::NextDay),
+    (KeyContext::Agenda, "pageup", Action::PreviousMonth),
+    (KeyContext::Agenda, "pagedown", Action::NextMonth),
+    (KeyContext::Agenda, ".", Action::Today),
+    (KeyContext::Agenda, "esc", Action::Cancel),
+    (KeyContext::Agenda, "enter", Action::ToggleDone),
+    (KeyContext::Agenda, "p", Action::Priority),
+    (KeyContext::Agenda, "D", Action::DueDate),
+    (KeyContext::Agenda, "t", Action::Tags),
+    (KeyContext::Agenda, "/", Action::Search),
+    (KeyContext::Agenda, "o", Action::Sort),
+    (KeyContext::Agenda, "i", Action::Details),
+    (KeyContext::Agenda, "n", Action::Notes),
+    (KeyContext::Agenda, "u", Action::Undo),
+    (KeyContext::Agenda, "T", Action::Theme),
+    (KeyContext::Agenda, "tab", Action::SwitchView),
+    (KeyContext::Agenda, "+", Action::ZoomIn),
+    (KeyContext::Agenda, "-", Action::ZoomOut),
+    (KeyContext::Agenda, "?", Action::Help),
+    (KeyContext::Agenda, ":", Action::Command),
+    (KeyContext::Agenda, "ctrl-p", Action::Picker),
+    (KeyContext::Agenda, "b", Action::Sidebar),
+    (KeyContext::Agenda, "]", Action::NextProject),
+    (KeyContext::Agenda, "[", Action::PreviousProject),
+    (KeyContext::Agenda, "c", Action::Status),
+    (KeyContext::Agenda, "B", Action::Depends),
+    (KeyContext::Agenda, "w", Action::Timer),
+    (KeyContext::Agenda, "O", Action::Pomodoro),
+    (KeyContext::Stats, "q", Action::Quit),
+    (KeyContext::Stats, "T", Action::Theme),
+    (KeyContext::Stats, "tab", Action::SwitchView),
+    (KeyContext::Stats, "+", Action::ZoomIn),
+    (KeyContext::Stats, "-", Action::ZoomOut),
+    (KeyContext::Stats, "?", Action::Help),
+    (KeyContext::Stats, ":", Action::Command),
+    (KeyContext::Stats, "ctrl-p", Action::Picker),
+    (KeyContext::Stats, "b", Action::Sidebar),
+    (KeyContext::Stats, "]", Action::NextProject),
+    (KeyContext::Stats, "[", Action::PreviousProject),
+    (KeyContext::Archive, "q", Action::Quit),
+    (KeyContext::Archive, "down", Action::Down),
+    (KeyContext::Archive, "up", Action::Up),
+    (KeyContext::Archive, "home", Action::Top),
+    (KeyContext::Archive, "end", Action::Bottom),
+    (KeyContext::Archive, "r", Action::Restore),
+    (KeyContext::Archive, "d", Action::Delete),
+    (KeyContext::Archive, "X", Action::Purge),
+    (KeyContext::Archive, "/", Action::Search),
+    (KeyContext::Archive, "o", Action::Sort),
+    (KeyContext::Archive, "i", Action::Details),
+    (KeyContext::Archive, "tab", Action::SwitchView),
+    (KeyContext::Archive, "+", Action::ZoomIn),
+    (KeyContext::Archive, "-", Action::ZoomOut),
+    (KeyContext::Archive, "?", Action::Help),
+    (KeyContext::Archive, ":", Action::Command),
+    (KeyContext::Archive, "ctrl-p", Action::Picker),
+    (KeyContext::Archive, "b", Action::Sidebar),
+    (KeyContext::Archive, "]", Action::NextProject),
+    (KeyContext::Archive, "[", Action::PreviousProject),
+    (KeyContext::Select, "down", Action::Down),
+    (KeyContext::Select, "up", Action::Up),
+    (KeyContext::Select, "space", Action::Mark),
+    (KeyContext::Select, "*", Action::MarkAll),
+    (KeyContext::Select, "enter", Action::ToggleDone),
+    (KeyContext::Select, "p", Action::Priority),
+    (KeyContext::Select, "D", Action::DueDate),
+    (KeyContext::Select, "t", Action::Tags),
+    (KeyContext::Select, "d", Action::Delete),
+    (KeyContext::Select, "u", Action::Undo),
+    (KeyContext::Select, "esc", Action::Cancel),
+    (KeyContext::Select, "v", Action::Cancel),
+    (KeyContext::Select, "?", Action::Help),
+    (KeyContext::Select, ":", Action::Command),
+    (KeyContext::Select, "ctrl-p", Action::Picker),
+    (KeyContext::Select, "m", Action::MoveToProject),
+    (KeyContext::Select, "c", Action::Status),
+    (KeyContext::Select, "B", Action::Depends),
+    (KeyContext::Input, "enter", Action::Submit),
+    (KeyContext::Input, "esc", Action::Cancel),
+    (KeyContext::Search, "enter", Action::Submit),
+    (KeyContext::Search, "esc", Action::Cancel),
+    (KeyContext::Search, "tab", Action::SearchArchive),
+    (KeyContext::Notes, "ctrl-s", Action::Submit),
+    (KeyContext::Notes, "enter", Action::NewLine),
+    (KeyContext::Notes, "esc", Action::Cancel),
+    (KeyContext::Confirm, "y", Action::Submit),
+    (KeyContext::Confirm, "enter", Action::Submit),
+    (KeyContext::Confirm, "n", Action::Cancel),
+    (KeyContext::Confirm, "esc", Action::Cancel),
+    (KeyContext::Command, "enter", Action::Submit),
+    (KeyContext::Command, "esc", Action::Cancel),
+    (KeyContext::Command, "tab", Action::Complete),
+    (KeyContext::Picker, "down", Action::Down),
+    (KeyContext::Picker, "ctrl-n", Action::Down),
+    (KeyContext::Picker, "up", Action::Up),
+    (KeyContext::Picker, "ctrl-p", Action::Up),
+    (KeyContext::Picker, "enter", Action::Submit),
+    (KeyContext::Picker, "esc", Action::Cancel),
+    (KeyContext::Help, "down", Action::Down),
+    (KeyContext::Help, "j", Action::Down),
+    (KeyContext::Help, "up", Action::Up),
+    (KeyContext::Help, "k", Action::Up),
+    (KeyContext::Help, "esc", Action::Cancel),
+    (KeyContext::Help, "?", Action::Cancel),
+    (KeyContext::Help, "q", Action::Cancel),
+];
+
+/// Vim keys. `dd` replaces `d` for deleting, as a single `d` is too easy
+/// to hit by accident once `j` and `k` are under the same hand.
+const VIM_BINDINGS: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::Tasks, "j", Action::Down),
+    (KeyContext::Tasks, "k", Action::Up),
+    (KeyContext::Tasks, "gg", Action::Top),
+    (KeyContext::Tasks, "G", Action::Bottom),
+    (KeyContext::Tasks, "dd", Action::Delete),
+    (KeyContext::Board, "j", Action::Down),
+    (KeyContext::Board, "k", Action::Up),
+    (KeyContext::Board, "dd", Action::Delete),
+    (KeyContext::Agenda, "j", Action::Down),
+    (KeyContext::Agenda, "k", Action::Up),
+    (KeyContext::Agenda, "gg", Action::Top),
+    (KeyContext::Agenda, "G", Action::Bottom),
+    (KeyContext::Agenda, "h", Action::PreviousDay),
+    (KeyContext::Agenda, "l", Action::NextDay),
+    (KeyContext::Archive, "j", Action::Down),
+    (KeyContext::Archive, "k", Action::Up),
+    (KeyContext::Archive, "gg", Action::Top),
+    (KeyContext::Archive, "G", Action::Bottom),
+    (KeyContext::Archive, "dd", Action::Delete),
+    (KeyContext::Select, "j", Action::Down),
+    (KeyContext::Select, "k", Action::Up),
+    (KeyContext::Select, "gg", Action::Top),
+    (KeyContext::Select, "G", Action::Bottom),
+];
+
+const EMACS_BINDINGS: &[(KeyContext, &str, Action)] = &[
+    (KeyContext::Tasks, "ctrl-x ctrl-c", Action::Quit),
+    (KeyContext::Tasks, "ctrl-n", Action::Down),
+    (KeyContext::Tasks, "ctrl-p", Action::Up),
+    (KeyContext::Tasks, "alt-<", Action::Top),
+    (KeyContext::Tasks, "alt->", Action::Bottom),
+    (KeyContext::Tasks, "alt-n", Action::MoveDown),
+    (KeyContext::Tasks, "alt-p", Action::MoveUp),
+    (KeyContext::Tasks, "ctrl-k", Action::Delete),
+    (KeyContext::Tasks, "ctrl-s", Action::Search),
+    (KeyContext::Tasks, "ctrl-_", Action::Undo),
+    (KeyContext::Tasks, "ctrl-/", Action::Undo),
+    (KeyContext::Tasks, "alt-x", Action::Command),
+    (KeyContext::Board, "ctrl-x ctrl-c", Action::Quit),
+    (KeyContext::Board, "ctrl-n", Action::Down),
+    (KeyContext::Board, "ctrl-p", Action::Up),
+    (KeyContext::Board, "ctrl-b", Action::Left),
+    (KeyContext::Board, "ctrl-f", Action::Right),
+    (KeyContext::Board, "ctrl-k", Action::Delete),
+    (KeyContext::Board, "ctrl-s", Action::Search),
+    (KeyContext::Board, "ctrl-_", Action::Undo),
+    (KeyContext::Board, "ctrl-/", Action::Undo),
+    (KeyContext::Board, "alt-x", Action::Command),
+    (KeyContext::Agenda, "ctrl-x ctrl-c", Action::Quit),
+    (KeyContext::Agenda, "ctrl-n", Action::Down),
+    (KeyContext::Agenda, "ctrl-p", Action::Up),
+    (KeyContext::Agenda, "alt-<", Action::Top),
+    (KeyContext::Agenda, "alt->", Action::Bottom),
+    (KeyContext::Agenda, "ctrl-b", Action::PreviousDay),
+    (KeyContext::Agenda, "ctrl-f", Action::NextDay),
+    (KeyContext::Agenda, "ctrl-s", Action::Search),
+    (KeyContext::Agenda, "ctrl-g", Action::Cancel),
+    (KeyContext::Agenda, "ctrl-_", Action::Undo),
+    (KeyContext::Agenda, "ctrl-/", Action::Undo),
+    (KeyContext::Agenda, "alt-x", Action::Command),
+    (KeyContext::Stats, "ctrl-x ctrl-c", Action::Quit),
+    (KeyContext::Stats, "alt-x", Action::Command),
+    (KeyContext::Archive, "ctrl-x ctrl-c", Action::Quit),
+    (KeyContext::Archive, "ctrl-n", Action::Down),
+    (KeyContext::Archive, "ctrl-p", Action::Up),
+    (KeyContext::Archive, "alt-<", Action::Top),
+    (KeyContext::Archive, "alt->", Action::Bottom),
+    (KeyContext::Archive, "ctrl-k", Action::Delete),
+    (KeyContext::Archive, "ctrl-s", Action::Search),
+    (KeyContext::Select, "ctrl-n", Action::Down),
+    (KeyContext::Select, "ctrl-p", Action::Up),
+    (KeyContext::Select, "ctrl-k", Action::Delete),
+    (KeyContext::Select, "ctrl-_", Action::Undo),
+    (KeyContext::Select, "ctrl-/", Action::Undo),
+    (KeyContext::Select, "ctrl-g", Action::Cancel),
+    (KeyContext::Input, "ctrl-g", Action::Cancel),
+    (KeyContext::Search, "ctrl-g", Action::Cancel),
+    (KeyContext::Notes, "ctrl-x ctrl-s", Action::Submit),
+    (KeyContext::Notes, "ctrl-g", Action::Cancel),
+    (KeyContext::Confirm, "ctrl-g", Action::Cancel),
+    (KeyContext::Command, "ctrl-g", Action::Cancel),
+    (KeyContext::Picker, "ctrl-g", Action::Cancel),
+    (KeyContext::Tasks, "ctrl-c p", Action::Picker),
+    (KeyContext::Archive, "ctrl-c p", Action::Picker),
+    (KeyContext::Select, "ctrl-c p", Action::Picker),
+    (KeyContext::Board, "ctrl-c p", Action::Picker),
+    (KeyContext::Agenda, "ctrl-c p", Action::Picker),
+];
+
+impl Keymap {
+    pub fn preset(preset: Preset) -> Keymap {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+        keymap.bind_all(DEFAULT_BINDINGS);
+        match preset {
+            Preset::Default => {}
+            Preset::Vim => {
+                keymap.unbind(KeyContext::Tasks, Action::Delete);
+                keymap.unbind(KeyContext::Archive, Action::Delete);
+                keymap.unbind(KeyContext::Board, Action::Delete);
+                keymap.bind_all(VIM_BINDINGS);
+            }
+            Preset::Emacs => {
+                // `ctrl-p` moves up instead
+                for context in [
+                    KeyContext::Tasks,
+                    KeyContext::Board,
+                    KeyContext::Agenda,
+                    KeyContext::Archive,
+                    KeyContext::Select,
+                ] {
+                    keymap.unbind(context, Action::Picker);
+                }
+                keymap.bind_all(EMACS_BINDINGS);
+            }
+        }
+        keymap
+    }
+
+    /// Builds the keymap described by the `[keys]` config table. Bad
+    /// entries are skipped and reported.
+    pub fn from_config(config: &KeysConfig) -> (Keymap, Vec<String>) {
+        let mut keymap = Keymap::preset(config.preset);
+        let mut errors = Vec::new();
+        let mut names: Vec<&String> = config.contexts.keys().collect();
+        names.sort();
+        for name in names {
+            let Some(context) = KeyContext::ALL.into_iter().find(|c| c.name() == name) else {
+                errors.push(format!("keys.{}: unknown mode", name));
+                continue;
+            };
+            let mut entries: Vec<_> = config.contexts[name].iter().collect();
+            entries.sort_by_key(|(action, _)| action.as_str());
+            for (action_name, keys) in entries {
+                let Some(action) = Action::from_name(action_name) else {
+                    errors.push(format!("keys.{}.{}: unknown action", name, action_name));
+                    continue;
+                };
+                let keys = match keys {
+                    KeyList::One(keys) => vec![keys.clone()],
+                    KeyList::Many(keys) => keys.clone(),
+                };
+                keymap.unbind(context, action);
+                for text in keys {
+                    match parse_keys(&text) {
+                        Ok(keys) => keymap.bind(context, keys, action),
+                        Err(e) => errors.push(format!("keys.{}.{}: {}", name, action_name, e)),
+                    }
+                }
+            }
+        }
+        (keymap, errors)
+    }
+
+    fn bind_all(&mut self, bindings: &[(KeyContext, &str, Action)]) {
+        for &(context, keys, action) in bindings {
+            self.bind(context, parse_keys(keys).unwrap(), action);
+        }
+    }
+
+    fn bind(&mut self, context: KeyContext, keys: Vec<Key>, action: Action) {
+        self.bindings
+            .entry(context)
+            .or_default()
+            .push(Binding { keys, action });
+    }
+
+    fn unbind(&mut self, context: KeyContext, action: Action) {
+        if let Some(bindings) = self.bindings.get_mut(&context) {
+            bindings.retain(|binding| binding.action != action);
+        }
+    }
+
+    pub fn bindings(&self, context: KeyContext) -> &[Binding] {
+        self.bindings.get(&context).map_or(&[], |b| b.as_slice())
+    }
+
+    /// Each bound action in binding order, with all of its keys.
+    pub fn actions(&self, context: KeyContext) -> Vec<(Action, Vec<String>)> {
+        let mut actions: Vec<(Action, Vec<String>)> = Vec::new();
+        for binding in self.bindings(context) {
+            let keys = format_keys(&binding.keys);
+            match actions.iter_mut().find(|(action, _)| *action == binding.action) {
+                Some((_, all)) => all.push(keys),
+                None => actions.push((binding.action, vec![keys])),
+            }
+        }
+        actions
+    }
+
+    /// Every key sequence bound to `action`, formatted for display.
+    pub fn keys_for(&self, context: KeyContext, action: Action) -> Vec<String> {
+        self.bindings(context)
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| format_keys(&binding.keys))
+            .collect()
+    }
+
+    /// Looks up `event` following the keys already pressed in `pending`.
+    /// When a sequence breaks off, the latest key is tried on its own.
+    pub fn resolve(&self, context: KeyContext, pending: &mut Vec<Key>, event: KeyEvent) -> Resolved {
+        let key = Key::from_event(event);
+        let bindings = self.bindings(context);
+        pending.push(key);
+        loop {
+            if let Some(binding) = bindings.iter().find(|binding| binding.keys == *pending) {
+                pending.clear();
+                return Resolved::Action(binding.action);
+            }
+            if bindings.iter().any(|binding| binding.keys.starts_with(pending)) {
+                return Resolved::Pending;
+            }
+            if pending.len() == 1 {
+                pending.clear();
+                return Resolved::Unbound;
+            }
+            *pending = vec![key];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::new(code, modifiers)
+    }
+
+    fn press(keymap: &Keymap, pending: &mut Vec<Key>, code: KeyCode) -> Resolved {
+        keymap.resolve(KeyContext::Tasks, pending, KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn keys_parse_with_modifiers() {
+        assert_eq!(parse_keys("ctrl-s"), Ok(vec![key(KeyCode::Char('s'), KeyModifiers::CONTROL)]));
+        assert_eq!(
+            parse_keys("Ctrl-Alt-x"),
+            Ok(vec![key(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT)])
+        );
+        assert_eq!(parse_keys("shift-down"), Ok(vec![key(KeyCode::Down, KeyModifiers::SHIFT)]));
+        // Shift on a character becomes the upper case character
+        assert_eq!(parse_keys("shift-j"), parse_keys("J"));
+        assert_eq!(parse_keys("alt--"), Ok(vec![key(KeyCode::Char('-'), KeyModifiers::ALT)]));
+        assert_eq!(parse_keys("ctrl--"), Ok(vec![key(KeyCode::Char('-'), KeyModifiers::CONTROL)]));
+    }
+
+    #[test]
+    fn keys_parse_named_keys() {
+        assert_eq!(parse_keys("enter"), Ok(vec![key(KeyCode::Enter, KeyModifiers::NONE)]));
+        assert_eq!(parse_keys("Esc"), Ok(vec![key(KeyCode::Esc, KeyModifiers::NONE)]));
+        assert_eq!(parse_keys("f1"), Ok(vec![key(KeyCode::F(1), KeyModifiers::NONE)]));
+        assert_eq!(parse_keys("ctrl-f12"), Ok(vec![key(KeyCode::F(12), KeyModifiers::CONTROL)]));
+    }
+
+    #[test]
+    fn keys_parse_sequences() {
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+        assert_eq!(parse_keys("gg"), Ok(vec![g, g]));
+        assert_eq!(parse_keys("g g"), Ok(vec![g, g]));
+        let ctrl = |c| key(KeyCode::Char(c), KeyModifiers::CONTROL);
+        assert_eq!(parse_keys("ctrl-x ctrl-c"), Ok(vec![ctrl('x'), ctrl('c')]));
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        assert_eq!(parse_keys(""), Err("empty key".to_string()));
+        assert_eq!(parse_keys("  "), Err("empty key".to_string()));
+        assert_eq!(parse_keys("ctrl-nope"), Err("unknown key `ctrl-nope`".to_string()));
+    }
+
+    #[test]
+    fn resolve_matches_single_keys_and_sequences() {
+        let keymap = Keymap::preset(Preset::Vim);
+        let mut pending = Vec::new();
+        assert_eq!(press(&keymap, &mut pending, KeyCode::Char('j')), Resolved::Action(Action::Down));
+        assert_eq!(press(&keymap, &mut pending, KeyCode::Char('g')), Resolved::Pending);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(press(&keymap, &mut pending, KeyCode::Char('g')), Resolved::Action(Action::Top));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn resolve_starts_over_when_a_sequence_breaks_off() {
+        let keymap = Keymap::preset(Preset::Vim);
+        let mut pending = Vec::new();
+        assert_eq!(press(&keymap, &mut pending, KeyCode::Char('d')), Resolved::Pending);
+        // `dj` is not bound, so `j` counts on its own
+        assert_eq!(press(&keymap, &mut pending, KeyCode::Char('j')), Resolved::Action(Action::Down));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn resolve_reports_unbound_keys() {
+        let keymap = Keymap::preset(Preset::Default);
+        let mut pending = Vec::new();
+        assert_eq!(press(&keymap, &mut pending, KeyCode::F(12)), Resolved::Unbound);
+        assert!(pending.is_empty());
+        assert_eq!(
+            keymap.resolve(KeyContext::Input, &mut pending, KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Resolved::Unbound
+        );
+    }
+}
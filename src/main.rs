@@ -1,27 +1,75 @@
 use crate::{
-    app::{App, AppMode},
+    app::{App, AppMode, PendingAction, View},
+    command::{complete, parse, Command},
+    editor::edit_in_editor,
+    event::{next_event, AppEvent},
+    keymap::{Action, Resolved},
+    picker::{picker_items, PickerItem},
     ui::ui,
 };
+use chrono::Local;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, KeyCode, KeyEvent, KeyModifiers, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
+    style::Print,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
+    layout::{Position, Rect},
     Terminal,
 };
-use std::{error::Error, io};
+use std::{
+    env,
+    error::Error,
+    io,
+    process,
+    time::{Duration, Instant},
+};
 
+mod agenda;
 mod app;
+mod board;
+mod cli;
+mod command;
+mod config;
+mod document;
+mod editor;
+mod event;
+mod keymap;
+mod picker;
+mod pomodoro;
+mod report;
+mod sort;
+mod stats;
 mod task;
+mod theme;
 mod ui;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&args) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste,
+        EnableFocusChange
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -34,7 +82,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        DisableFocusChange
     )?;
     terminal.show_cursor()?;
 
@@ -45,66 +95,378 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// How long two clicks on the same row can be apart to count as a
+/// double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// How often the screen is redrawn while waiting for input, so that the
+/// pomodoro countdown and running timers keep moving.
+const TICK: Duration = Duration::from_secs(1);
+
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+    let mut last_click: Option<(Instant, usize)> = None;
     loop {
-        terminal.draw(|f| ui::<B>(f, &mut app))?;
-
-        if let Event::Key(key) = event::read()? {
-            match app.mode {
-                AppMode::Normal => match key.code {
-                    KeyCode::Char('q') => {
-                        app.save();
-                        return Ok(());
-                    }
-                    KeyCode::Down => app.next(),
-                    KeyCode::Up => app.previous(),
-                    KeyCode::Enter => app.toggle_completed(),
-                    KeyCode::Char('a') => {
-                        app.adding_subtask = false;
-                        app.mode = AppMode::Insert;
-                    },
-                    KeyCode::Char('d') => app.delete_task(),
-                    KeyCode::Char('p') => app.cycle_priority(),
-                    KeyCode::Char('D') => app.mode = AppMode::DateInput,
-                    KeyCode::Char('s') => {
-                        app.adding_subtask = true;
-                        app.mode = AppMode::Insert;
-                    },
-                    KeyCode::Char('/') => app.mode = AppMode::Search,
-                    KeyCode::Char('+') => app.zoom_in(),
-                    KeyCode::Char('-') => app.zoom_out(),
-                    _ => {}
-                },
-                AppMode::Insert => match key.code {
-                    KeyCode::Enter => app.add_task(),
-                    KeyCode::Char(c) => app.input.push(c),
-                    KeyCode::Backspace => {
-                        app.input.pop();
-                    }
-                    KeyCode::Esc => {
-                        app.adding_subtask = false;
-                        app.mode = AppMode::Normal;
-                    },
-                    _ => {}
-                },
-                AppMode::DateInput => match key.code {
-                    KeyCode::Enter => app.set_due_date(),
-                    KeyCode::Char(c) => app.date_input.push(c),
-                    KeyCode::Backspace => {
-                        app.date_input.pop();
-                    }
-                    KeyCode::Esc => app.mode = AppMode::Normal,
-                    _ => {}
-                },
-                AppMode::Search => match key.code {
-                    KeyCode::Enter | KeyCode::Esc => app.mode = AppMode::Normal,
-                    KeyCode::Char(c) => app.search_input.push(c),
+        terminal.draw(|f| ui(f, &mut app))?;
+
+        if app.tick() {
+            // Ring the terminal bell when a pomodoro phase ends
+            execute!(io::stdout(), Print('\x07'))?;
+        }
+
+        let Some(event) = next_event(TICK)? else {
+            continue;
+        };
+        let quit = match event {
+            AppEvent::Key(key) => handle_key(terminal, &mut app, key)?,
+            AppEvent::Mouse(mouse) => handle_mouse(terminal, &mut app, mouse, &mut last_click)?,
+            AppEvent::Paste(text) => {
+                app.paste(&text);
+                false
+            }
+            AppEvent::Resize(width, height) => {
+                // Lay out for the new size right away. Rows may have moved,
+                // so a click before the resize no longer starts a double-click
+                terminal.resize(Rect::new(0, 0, width, height))?;
+                last_click = None;
+                false
+            }
+            // Save whenever the user switches away, so nothing is lost if
+            // the terminal is closed from outside
+            AppEvent::FocusLost => {
+                app.save();
+                false
+            }
+            AppEvent::FocusGained => false,
+        };
+        if quit {
+            return Ok(());
+        }
+    }
+}
+
+/// Returns true when the app should quit.
+fn handle_key<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, key: KeyEvent) -> io::Result<bool> {
+    app.status_message = None;
+    let context = app.key_context();
+    match app.keymap.resolve(context, &mut app.pending_keys, key) {
+        Resolved::Action(action) => return run_action(terminal, app, action),
+        Resolved::Pending => {}
+        // Keys without a binding are typed into the open input, if any
+        Resolved::Unbound => {
+            let typing = !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            if let Some(field) = app.input_field_mut() {
+                match key.code {
+                    KeyCode::Char(c) if typing => field.push(c),
                     KeyCode::Backspace => {
-                        app.search_input.pop();
+                        field.pop();
                     }
                     _ => {}
-                },
+                }
+            }
+            // The matches change as the query does
+            app.picker_selected = 0;
+        }
+    }
+    Ok(false)
+}
+
+/// Returns true when the app should quit.
+fn run_action<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, action: Action) -> io::Result<bool> {
+    match action {
+        Action::Quit => {
+            app.save();
+            return Ok(true);
+        }
+        Action::Down if matches!(app.mode, AppMode::Picker(_)) => {
+            app.picker_selected = app.picker_selected.saturating_add(1)
+        }
+        Action::Up if matches!(app.mode, AppMode::Picker(_)) => {
+            app.picker_selected = app.picker_selected.saturating_sub(1)
+        }
+        Action::Down if matches!(app.mode, AppMode::Help(_)) => {
+            app.help_scroll = app.help_scroll.saturating_add(1)
+        }
+        Action::Up if matches!(app.mode, AppMode::Help(_)) => {
+            app.help_scroll = app.help_scroll.saturating_sub(1)
+        }
+        Action::Down => app.next(),
+        Action::Up => app.previous(),
+        Action::Top => app.select_first(),
+        Action::Bottom => app.select_last(),
+        Action::MoveDown => app.move_down(),
+        Action::MoveUp => app.move_up(),
+        Action::Indent => app.indent(),
+        Action::Outdent => app.outdent(),
+        Action::ToggleDone => app.toggle_completed(),
+        Action::Add => {
+            app.adding_subtask = false;
+            app.mode = AppMode::Insert;
+        }
+        Action::AddSubtask => {
+            app.adding_subtask = true;
+            app.mode = AppMode::Insert;
+        }
+        Action::Delete if matches!(app.mode, AppMode::Select) && !app.marked.is_empty() => {
+            app.request(PendingAction::BulkDelete)
+        }
+        Action::Delete => app.request(PendingAction::Delete),
+        Action::Priority => app.cycle_priority(),
+        Action::DueDate => app.mode = AppMode::DateInput,
+        Action::Tags => app.mode = AppMode::TagInput,
+        Action::Search => app.mode = AppMode::Search,
+        Action::Sort => app.cycle_sort(),
+        Action::Details => app.toggle_details(),
+        Action::Notes => app.start_notes(),
+        Action::NotesInEditor => {
+            if let Some(notes) = app.editable_task().map(|t| t.notes.clone()) {
+                match edit_in_editor(terminal, &notes, "md") {
+                    Ok(notes) => app.set_notes(notes),
+                    Err(e) => app.status_message = Some(format!("Editor: {}", e)),
+                }
+            }
+        }
+        Action::EditDocument => match edit_in_editor(terminal, &app.document(), "md") {
+            Ok(text) => app.apply_document(&text),
+            Err(e) => app.status_message = Some(format!("Editor: {}", e)),
+        },
+        Action::ArchiveDone => app.request(PendingAction::ArchiveAll),
+        Action::Restore => app.restore_task(),
+        Action::Purge => app.request(PendingAction::Purge),
+        Action::SwitchView => app.toggle_view(),
+        Action::Select => app.mode = AppMode::Select,
+        Action::Mark => app.toggle_mark(),
+        Action::MarkAll => app.mark_all(),
+        Action::Undo => app.undo(),
+        Action::Theme => app.cycle_theme(),
+        Action::Focus => app.toggle_focus(),
+        Action::FocusTask => app.toggle_single_focus(),
+        Action::ZoomIn => app.zoom_in(),
+        Action::ZoomOut => app.zoom_out(),
+        Action::Submit => match app.mode {
+            AppMode::Insert => app.add_task(),
+            AppMode::DateInput => app.set_due_date(),
+            AppMode::TagInput => app.edit_tags(),
+            AppMode::Search => app.leave_input(),
+            AppMode::Notes => app.save_notes(),
+            AppMode::Confirm(_) => app.confirm(),
+            AppMode::Command => {
+                let input = std::mem::take(&mut app.command_input);
+                app.leave_input();
+                match parse(&input) {
+                    Ok(command) => return run_command(terminal, app, command),
+                    Err(e) => app.status_message = Some(e),
+                }
+            }
+            AppMode::Picker(_) => {
+                let items = picker_items(app, &app.picker_input);
+                let item = items
+                    .get(app.picker_selected.min(items.len().saturating_sub(1)))
+                    .cloned();
+                app.picker_input.clear();
+                app.leave_input();
+                match item {
+                    Some(PickerItem::Action(action)) => return run_action(terminal, app, action),
+                    Some(PickerItem::View(name)) => app.apply_view(&name),
+                    None => {}
+                }
+            }
+            AppMode::Normal | AppMode::Select | AppMode::Help(_) => {}
+        },
+        Action::Cancel => match app.mode {
+            AppMode::Insert => {
+                app.adding_subtask = false;
+                app.leave_input();
+            }
+            AppMode::DateInput | AppMode::Confirm(_) | AppMode::Help(_) => app.leave_input(),
+            AppMode::TagInput => {
+                app.tag_input.clear();
+                app.leave_input();
+            }
+            AppMode::Notes => {
+                app.notes_input.clear();
+                app.leave_input();
+            }
+            AppMode::Search => {
+                app.search_input.clear();
+                app.leave_input();
+            }
+            AppMode::Command => {
+                app.command_input.clear();
+                app.leave_input();
+            }
+            AppMode::Picker(_) => {
+                app.picker_input.clear();
+                app.leave_input();
+            }
+            AppMode::Select => app.clear_marks(),
+            // Shows every day of the agenda again
+            AppMode::Normal if app.view == View::Agenda => app.select_day(None),
+            AppMode::Normal => {}
+        },
+        Action::NewLine => app.notes_input.push('\n'),
+        Action::SearchArchive => app.search_archive = !app.search_archive,
+        Action::Command => {
+            app.command_input.clear();
+            app.mode = AppMode::Command;
+        }
+        Action::Picker => {
+            app.picker_input.clear();
+            app.picker_selected = 0;
+            app.mode = AppMode::Picker(app.key_context());
+        }
+        Action::Complete => {
+            let (input, candidates) = complete(&app.command_input, app);
+            app.command_input = input;
+            if !candidates.is_empty() {
+                app.status_message = Some(candidates.join("  "));
+            }
+        }
+        Action::Help => {
+            app.help_scroll = 0;
+            app.mode = AppMode::Help(app.key_context());
+        }
+        Action::Sidebar => app.toggle_sidebar(),
+        Action::NextProject => app.cycle_project(true),
+        Action::PreviousProject => app.cycle_project(false),
+        Action::Left => app.select_column(-1),
+        Action::Right => app.select_column(1),
+        Action::MoveLeft => app.move_card(-1),
+        Action::MoveRight => app.move_card(1),
+        Action::Group => app.cycle_board_grouping(),
+        Action::PreviousDay => app.step_day(-1),
+        Action::NextDay => app.step_day(1),
+        Action::PreviousMonth => app.step_month(-1),
+        Action::NextMonth => app.step_month(1),
+        Action::Today => app.select_day(Some(Local::now().date_naive())),
+        Action::Timer => app.toggle_timer(),
+        Action::Pomodoro => app.toggle_pomodoro(),
+        Action::Depends => {
+            app.command_input = "depends ".to_string();
+            app.mode = AppMode::Command;
+        }
+        Action::Status => {
+            app.command_input = "status ".to_string();
+            app.mode = AppMode::Command;
+        }
+        Action::MoveToProject => {
+            app.command_input = "move ".to_string();
+            app.mode = AppMode::Command;
+        }
+    }
+    Ok(false)
+}
+
+/// Returns true when the app should quit.
+fn run_command<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, command: Command) -> io::Result<bool> {
+    match command {
+        Command::Action(action) => return run_action(terminal, app, action),
+        Command::Sort(sort_mode) => app.set_sort(sort_mode),
+        Command::Tag(edit) => {
+            app.tag_input = edit;
+            app.edit_tags();
+        }
+        Command::Due(date) => {
+            app.date_input = date;
+            app.set_due_date();
+        }
+        Command::ArchiveDone => app.request(PendingAction::ArchiveAll),
+        Command::Export(format, path) => app.export(format, &path),
+        Command::Theme(name) => app.set_theme(&name),
+        Command::View(view) => app.set_view(view),
+        Command::SaveView(name) => app.save_view(&name),
+        Command::ApplyView(name) => app.apply_view(&name),
+        Command::DeleteView(name) => app.delete_view(&name),
+        Command::Project(name) => app.switch_project(&name),
+        Command::AddProject(name) => app.add_project(&name),
+        Command::RenameProject(name) => app.rename_project(&name),
+        Command::ProjectColor(color) => app.set_project_color(&color),
+        Command::ProjectParent(name) => app.set_project_parent(&name),
+        Command::DeleteProject => app.request(PendingAction::DeleteProject),
+        Command::Move(name) => app.move_to_project(&name),
+        Command::Status(name, reason) => app.set_status(&name, &reason),
+        Command::Depends(ids) => app.edit_dependencies(&ids),
+        Command::Blocks(ids) => app.add_blocks(&ids),
+        Command::Report(args) => app.show_report(&args),
+        Command::Board(grouping) => {
+            app.set_board_grouping(grouping);
+            app.set_view(View::Board);
+        }
+        Command::Write => {
+            app.save();
+            app.status_message = Some("Saved".to_string());
+        }
+    }
+    Ok(false)
+}
+
+fn handle_mouse<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mouse: MouseEvent,
+    last_click: &mut Option<(Instant, usize)>,
+) -> io::Result<bool> {
+    let position = Position::new(mouse.column, mouse.row);
+    let in_list = matches!(app.mode, AppMode::Normal | AppMode::Select);
+    match mouse.kind {
+        MouseEventKind::ScrollDown if in_list => app.next(),
+        MouseEventKind::ScrollUp if in_list => app.previous(),
+        MouseEventKind::Down(MouseButton::Left) => {
+            // Clicking outside a popup cancels it, like Esc
+            if let Some(popup) = app.areas.popup {
+                if !popup.contains(position) {
+                    return run_action(terminal, app, Action::Cancel);
+                }
+                return Ok(false);
+            }
+            // Footer keys act as buttons
+            let footer_action = app
+                .areas
+                .footer_keys
+                .iter()
+                .find(|(area, _)| area.contains(position))
+                .map(|(_, action)| *action);
+            if let Some(action) = footer_action {
+                return run_action(terminal, app, action);
+            }
+            let project = app
+                .areas
+                .sidebar
+                .iter()
+                .find(|(area, _)| area.contains(position))
+                .map(|(_, filter)| *filter);
+            if let Some(filter) = project {
+                app.set_project_filter(filter);
+                return Ok(false);
+            }
+            let day = app
+                .areas
+                .calendar
+                .iter()
+                .find(|(area, _)| area.contains(position))
+                .map(|(_, day)| *day);
+            if let Some(day) = day {
+                app.select_day(Some(day));
+                return Ok(false);
+            }
+            if let Some(row) = app.row_at(position) {
+                let now = Instant::now();
+                let double_click = last_click
+                    .is_some_and(|(at, last_row)| last_row == row && now.duration_since(at) < DOUBLE_CLICK);
+                app.state.select(Some(row));
+                if let Some(column) = app.column_at(position) {
+                    app.board_column = column;
+                }
+                if double_click {
+                    app.toggle_completed();
+                    *last_click = None;
+                } else {
+                    *last_click = Some((now, row));
+                }
             }
         }
+        _ => {}
     }
-}
\ No newline at end of file
+    Ok(false)
+}
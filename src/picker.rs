@@ -0,0 +1,130 @@
+use crate::app::App;
+use crate::keymap::Action;
+use std::cmp::Reverse;
+
+/// An entry in the Ctrl-P picker.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PickerItem {
+    Action(Action),
+    /// A saved view, by name.
+    View(String),
+}
+
+impl PickerItem {
+    pub fn label(&self) -> String {
+        match self {
+            PickerItem::Action(action) => action.description().to_string(),
+            PickerItem::View(name) => format!("View: {}", name),
+        }
+    }
+
+    /// The texts the query is matched against; the best match counts.
+    fn search_texts(&self) -> Vec<String> {
+        match self {
+            PickerItem::Action(action) => {
+                vec![action.name().to_string(), action.description().to_string()]
+            }
+            PickerItem::View(name) => vec![name.clone(), format!("view {}", name)],
+        }
+    }
+}
+
+/// Actions that only mean something inside an input or prompt.
+fn pickable(action: Action) -> bool {
+    !matches!(
+        action,
+        Action::Submit
+            | Action::Cancel
+            | Action::NewLine
+            | Action::Complete
+            | Action::SearchArchive
+            | Action::Picker
+    )
+}
+
+/// Saved views and actions matching `query`, best match first. Ties keep
+/// views before actions and actions in keymap order.
+pub fn picker_items(app: &App, query: &str) -> Vec<PickerItem> {
+    let items = app
+        .views
+        .iter()
+        .map(|view| PickerItem::View(view.name.clone()))
+        .chain(
+            Action::ALL
+                .into_iter()
+                .filter(|&action| pickable(action))
+                .map(PickerItem::Action),
+        );
+    let mut scored: Vec<(i32, PickerItem)> = items
+        .filter_map(|item| {
+            let score = item
+                .search_texts()
+                .iter()
+                .filter_map(|text| fuzzy_score(query, text))
+                .max()?;
+            Some((score, item))
+        })
+        .collect();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Scores `text` against `query` if every character of the query appears
+/// in it in order, ignoring case and spaces. Runs of consecutive characters
+/// and matches at the start of a word score higher; gaps between matches
+/// and long texts score lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = -(text.len() as i32 / 8);
+    let mut from = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = (from..text.len()).find(|&i| text[i] == c)?;
+        score += 1;
+        match previous {
+            Some(p) if p + 1 == i => score += 4,
+            Some(p) => score -= (i - p - 1).min(5) as i32,
+            None => {}
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(i);
+        from = i + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_query_character_has_to_appear_in_order() {
+        assert!(fuzzy_score("thm", "theme").is_some());
+        assert_eq!(fuzzy_score("mht", "theme"), None);
+        assert_eq!(fuzzy_score("themes", "theme"), None);
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn case_and_spaces_in_the_query_are_ignored() {
+        assert_eq!(fuzzy_score("Cycle Theme", "cycle the theme"), fuzzy_score("cycletheme", "Cycle the theme"));
+    }
+
+    #[test]
+    fn consecutive_matches_beat_scattered_ones() {
+        assert!(fuzzy_score("sort", "sort mode") > fuzzy_score("sort", "show other rows today"));
+    }
+
+    #[test]
+    fn matches_at_word_starts_score_higher() {
+        assert!(fuzzy_score("d", "due date") > fuzzy_score("d", "add task"));
+        assert!(fuzzy_score("ma", "mark all") > fuzzy_score("ma", "remark"));
+    }
+
+    #[test]
+    fn shorter_texts_win_ties() {
+        assert!(fuzzy_score("undo", "undo") > fuzzy_score("undo", "undo the last change to a task"));
+    }
+}
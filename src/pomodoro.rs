@@ -0,0 +1,156 @@
+use crate::config::PomodoroConfig;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Work,
+    Break,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Work => "work",
+            Phase::Break => "break",
+            Phase::LongBreak => "long break",
+        }
+    }
+
+    pub fn minutes(self, config: &PomodoroConfig) -> u32 {
+        match self {
+            Phase::Work => config.work_minutes,
+            Phase::Break => config.break_minutes,
+            Phase::LongBreak => config.long_break_minutes,
+        }
+    }
+
+    fn length(self, config: &PomodoroConfig) -> Duration {
+        Duration::from_secs(u64::from(self.minutes(config)) * 60)
+    }
+}
+
+/// A running pomodoro: work and breaks in turn on one task, until stopped.
+#[derive(Debug, Clone)]
+pub struct Pomodoro {
+    pub task_id: usize,
+    pub phase: Phase,
+    pub ends_at: Instant,
+    /// Work phases finished since it started.
+    pub completed: u32,
+}
+
+impl Pomodoro {
+    pub fn start(task_id: usize, config: &PomodoroConfig) -> Pomodoro {
+        Pomodoro {
+            task_id,
+            phase: Phase::Work,
+            ends_at: Instant::now() + Phase::Work.length(config),
+            completed: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.ends_at.saturating_duration_since(Instant::now())
+    }
+
+    /// Moves on to the next phase once this one is over, returning the
+    /// phase that ended. Every `long_break_every` work phases earn a long
+    /// break instead of a short one.
+    pub fn advance(&mut self, config: &PomodoroConfig) -> Option<Phase> {
+        let now = Instant::now();
+        if now < self.ends_at {
+            return None;
+        }
+        let ended = self.phase;
+        self.phase = match ended {
+            Phase::Work => {
+                self.completed += 1;
+                if config.long_break_every > 0 && self.completed.is_multiple_of(config.long_break_every) {
+                    Phase::LongBreak
+                } else {
+                    Phase::Break
+                }
+            }
+            Phase::Break | Phase::LongBreak => Phase::Work,
+        };
+        self.ends_at = now + self.phase.length(config);
+        Some(ended)
+    }
+}
+
+/// Formats what is left of a phase as `mm:ss`.
+pub fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(long_break_every: u32) -> PomodoroConfig {
+        PomodoroConfig {
+            long_break_every,
+            ..PomodoroConfig::default()
+        }
+    }
+
+    /// Ends the current phase now and advances, returning the new phase.
+    fn finish(pomodoro: &mut Pomodoro, config: &PomodoroConfig) -> Phase {
+        pomodoro.ends_at = Instant::now();
+        assert!(pomodoro.advance(config).is_some());
+        pomodoro.phase
+    }
+
+    #[test]
+    fn advance_waits_for_the_phase_to_end() {
+        let config = config(4);
+        let mut pomodoro = Pomodoro::start(1, &config);
+        assert_eq!(pomodoro.advance(&config), None);
+        assert_eq!(pomodoro.phase, Phase::Work);
+    }
+
+    #[test]
+    fn advance_rolls_over_between_work_and_breaks() {
+        let config = config(4);
+        let mut pomodoro = Pomodoro::start(1, &config);
+        pomodoro.ends_at = Instant::now();
+        assert_eq!(pomodoro.advance(&config), Some(Phase::Work));
+        assert_eq!(pomodoro.phase, Phase::Break);
+        assert_eq!(pomodoro.completed, 1);
+        assert!(pomodoro.remaining() > Duration::from_secs(4 * 60));
+        assert_eq!(finish(&mut pomodoro, &config), Phase::Work);
+        assert_eq!(pomodoro.completed, 1);
+    }
+
+    #[test]
+    fn every_nth_break_is_long() {
+        let config = config(2);
+        let mut pomodoro = Pomodoro::start(1, &config);
+        let phases: Vec<Phase> = (0..8).map(|_| finish(&mut pomodoro, &config)).collect();
+        assert_eq!(
+            phases,
+            [
+                Phase::Break,
+                Phase::Work,
+                Phase::LongBreak,
+                Phase::Work,
+                Phase::Break,
+                Phase::Work,
+                Phase::LongBreak,
+                Phase::Work,
+            ]
+        );
+        assert_eq!(pomodoro.completed, 4);
+    }
+
+    #[test]
+    fn no_long_breaks_when_turned_off() {
+        let config = config(0);
+        let mut pomodoro = Pomodoro::start(1, &config);
+        for _ in 0..10 {
+            assert_ne!(finish(&mut pomodoro, &config), Phase::LongBreak);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::app::App;
+use chrono::prelude::*;
+use chrono::Duration;
+use chrono_english::{parse_date_string, Dialect};
+use std::collections::HashMap;
+
+/// What a time report totals by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportGrouping {
+    Tag,
+    Project,
+}
+
+impl ReportGrouping {
+    pub fn from_name(name: &str) -> Option<ReportGrouping> {
+        match name {
+            "tag" | "tags" => Some(ReportGrouping::Tag),
+            "project" | "projects" => Some(ReportGrouping::Project),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReportGrouping::Tag => "tag",
+            ReportGrouping::Project => "project",
+        }
+    }
+}
+
+/// Time tracked between two days, both included.
+pub struct Report {
+    pub grouping: ReportGrouping,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Each tag or project with its time, most time first. A task with
+    /// several tags counts toward each of them.
+    pub rows: Vec<(String, Duration)>,
+    pub total: Duration,
+}
+
+/// Parses `report`'s arguments: an optional grouping, then a range such as
+/// `monday`, `2025-01-01 to 2025-01-31` or `last friday to yesterday`. The
+/// range ends today unless given, and starts a week back.
+pub fn parse_args(args: &str) -> Result<(ReportGrouping, NaiveDate, NaiveDate), String> {
+    parse_args_on(args, Local::now().date_naive())
+}
+
+fn parse_args_on(args: &str, today: NaiveDate) -> Result<(ReportGrouping, NaiveDate, NaiveDate), String> {
+    let args = args.trim();
+    let (word, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let (grouping, range) = match ReportGrouping::from_name(word) {
+        Some(grouping) => (grouping, rest.trim()),
+        None => (ReportGrouping::Tag, args),
+    };
+    let (from, to) = match range.split_once(" to ") {
+        Some((from, to)) => (parse_day(from, today)?, parse_day(to, today)?),
+        None if range.is_empty() => (today - Duration::days(6), today),
+        None => (parse_day(range, today)?, today),
+    };
+    if from > to {
+        return Err(format!("The report starts after it ends: {} to {}", from, to));
+    }
+    Ok((grouping, from, to))
+}
+
+/// Reads a day of the range. A bare weekday is the last one up to today,
+/// since a report looks back.
+fn parse_day(text: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let text = text.trim();
+    if let Ok(weekday) = text.parse::<Weekday>() {
+        let back = (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        return Ok(today - Duration::days(back.into()));
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .or_else(|_| parse_date_string(text, start_of(today), Dialect::Us).map(|date| date.date_naive()))
+        .map_err(|_| format!("Not a date: {}", text))
+}
+
+pub fn report(app: &App, grouping: ReportGrouping, from: NaiveDate, to: NaiveDate) -> Report {
+    let start = start_of(from);
+    let end = start_of(to + Duration::days(1));
+    let mut times: HashMap<String, Duration> = HashMap::new();
+    let mut total = Duration::zero();
+    for top in app.tasks.iter().chain(&app.archive) {
+        // Subtasks go with their top-level task's project
+        let project = app.task_project(top).map_or("Inbox".to_string(), |project| project.name.clone());
+        for task in top.with_subtasks() {
+            let time = task.time_between(start, end);
+            if time.is_zero() {
+                continue;
+            }
+            total += time;
+            let keys = match grouping {
+                ReportGrouping::Project => vec![project.clone()],
+                ReportGrouping::Tag if task.tags.is_empty() => vec!["(untagged)".to_string()],
+                ReportGrouping::Tag => task.tags.clone(),
+            };
+            for key in keys {
+                *times.entry(key).or_insert_with(Duration::zero) += time;
+            }
+        }
+    }
+    let mut rows: Vec<(String, Duration)> = times.into_iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Report {
+        grouping,
+        from,
+        to,
+        rows,
+        total,
+    }
+}
+
+/// Local midnight at the start of `day`.
+fn start_of(day: NaiveDate) -> DateTime<Local> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+/// Formats a duration as hours and minutes, e.g. `2h 05m` or `40m`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    /// A Wednesday.
+    fn today() -> NaiveDate {
+        day("2025-03-12")
+    }
+
+    #[test]
+    fn the_default_range_is_the_last_week() {
+        assert_eq!(parse_args_on("", today()), Ok((ReportGrouping::Tag, day("2025-03-06"), today())));
+        assert_eq!(
+            parse_args_on("project", today()),
+            Ok((ReportGrouping::Project, day("2025-03-06"), today()))
+        );
+    }
+
+    #[test]
+    fn weekdays_look_back() {
+        assert_eq!(parse_args_on("monday", today()), Ok((ReportGrouping::Tag, day("2025-03-10"), today())));
+        assert_eq!(parse_args_on("tags Thu", today()), Ok((ReportGrouping::Tag, day("2025-03-06"), today())));
+        assert_eq!(parse_args_on("wednesday", today()), Ok((ReportGrouping::Tag, today(), today())));
+    }
+
+    #[test]
+    fn ranges_take_dates_and_phrases() {
+        assert_eq!(
+            parse_args_on("project 2025-01-01 to 2025-01-31", today()),
+            Ok((ReportGrouping::Project, day("2025-01-01"), day("2025-01-31")))
+        );
+        assert_eq!(
+            parse_args_on("monday to yesterday", today()),
+            Ok((ReportGrouping::Tag, day("2025-03-10"), day("2025-03-11")))
+        );
+    }
+
+    #[test]
+    fn bad_ranges_are_refused() {
+        assert_eq!(parse_args_on("soon-ish", today()), Err("Not a date: soon-ish".to_string()));
+        assert_eq!(
+            parse_args_on("2025-02-01 to 2025-01-01", today()),
+            Err("The report starts after it ends: 2025-02-01 to 2025-01-01".to_string())
+        );
+    }
+
+    #[test]
+    fn durations_read_as_hours_and_minutes() {
+        assert_eq!(format_duration(Duration::minutes(40)), "40m");
+        assert_eq!(format_duration(Duration::minutes(125)), "2h 05m");
+    }
+}
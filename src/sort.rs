@@ -0,0 +1,173 @@
+use crate::task::Task;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// A single comparison step. Sort modes chain these together, falling
+/// through to the next key whenever two tasks compare equal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Overdue,
+    Priority,
+    DueDate,
+    Created,
+    Alphabetical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortMode {
+    #[default]
+    Manual,
+    Priority,
+    DueDate,
+    Created,
+    Alphabetical,
+    /// Overdue first, then priority, then due date.
+    Smart,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 6] = [
+        SortMode::Manual,
+        SortMode::Priority,
+        SortMode::DueDate,
+        SortMode::Created,
+        SortMode::Alphabetical,
+        SortMode::Smart,
+    ];
+
+    pub fn next(self) -> SortMode {
+        let i = SortMode::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        SortMode::ALL[(i + 1) % SortMode::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Manual => "manual",
+            SortMode::Priority => "priority",
+            SortMode::DueDate => "due",
+            SortMode::Created => "created",
+            SortMode::Alphabetical => "alpha",
+            SortMode::Smart => "overdue/priority/due",
+        }
+    }
+
+    /// The name used by the `:sort` command.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Smart => "smart",
+            mode => mode.label(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SortMode> {
+        SortMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn keys(self) -> &'static [SortKey] {
+        match self {
+            SortMode::Manual => &[],
+            SortMode::Priority => &[SortKey::Priority, SortKey::DueDate],
+            SortMode::DueDate => &[SortKey::DueDate, SortKey::Priority],
+            SortMode::Created => &[SortKey::Created],
+            SortMode::Alphabetical => &[SortKey::Alphabetical],
+            SortMode::Smart => &[SortKey::Overdue, SortKey::Priority, SortKey::DueDate],
+        }
+    }
+
+    /// Compares two sibling tasks. `today` is the current date formatted as
+    /// `%Y-%m-%d`, the same format `Task.due_date` is stored in.
+    pub fn compare(self, a: &Task, b: &Task, today: &str) -> Ordering {
+        self.keys()
+            .iter()
+            .map(|key| key.compare(a, b, today))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl SortKey {
+    fn compare(self, a: &Task, b: &Task, today: &str) -> Ordering {
+        match self {
+            SortKey::Overdue => b.is_overdue(today).cmp(&a.is_overdue(today)),
+            SortKey::Priority => b.priority.cmp(&a.priority),
+            // Due dates kept as free text go after real dates, and tasks
+            // without a due date go last.
+            SortKey::DueDate => match (a.due_day(), b.due_day()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => match (&a.due_date, &b.due_date) {
+                    (Some(a), Some(b)) => a.cmp(b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+            },
+            // Newest first. Tasks from before timestamps were recorded have
+            // none, but their IDs still follow creation order.
+            SortKey::Created => b
+                .created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.id.cmp(&a.id)),
+            SortKey::Alphabetical => a
+                .description
+                .to_lowercase()
+                .cmp(&b.description.to_lowercase()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Status;
+    use serde_json::json;
+
+    const TODAY: &str = "2025-03-10";
+
+    fn task(id: usize, due_date: Option<&str>) -> Task {
+        serde_json::from_value(json!({
+            "id": id,
+            "description": format!("Task {}", id),
+            "priority": "Medium",
+            "due_date": due_date,
+            "sub_tasks": [],
+            "tags": [],
+        }))
+        .unwrap()
+    }
+
+    fn sorted(mode: SortMode, mut tasks: Vec<Task>) -> Vec<usize> {
+        tasks.sort_by(|a, b| mode.compare(a, b, TODAY));
+        tasks.iter().map(|task| task.id).collect()
+    }
+
+    #[test]
+    fn due_dates_sort_by_day_then_free_text_then_none() {
+        let tasks = vec![
+            task(1, None),
+            task(2, Some("1st of may")),
+            task(3, Some("2025-12-01")),
+            task(4, Some("2025-02-28")),
+            task(5, Some("2026-01-01")),
+        ];
+        assert_eq!(sorted(SortMode::DueDate, tasks), [4, 3, 5, 2, 1]);
+    }
+
+    #[test]
+    fn free_text_due_dates_are_never_overdue() {
+        let mut done = task(3, Some("2025-01-01"));
+        done.status = Status::Done;
+        assert!(task(1, Some("2025-03-09")).is_overdue(TODAY));
+        assert!(!task(2, Some("2025-03-10")).is_overdue(TODAY));
+        assert!(!task(4, Some("1st of may")).is_overdue(TODAY));
+        assert!(!task(5, Some("0")).is_overdue(TODAY));
+        assert!(!done.is_overdue(TODAY));
+    }
+
+    #[test]
+    fn smart_sort_puts_overdue_tasks_first() {
+        let tasks = vec![task(1, Some("1st of may")), task(2, Some("2025-03-11")), task(3, Some("2025-03-01"))];
+        assert_eq!(sorted(SortMode::Smart, tasks), [3, 2, 1]);
+    }
+}
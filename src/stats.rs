@@ -0,0 +1,152 @@
+use crate::app::App;
+use crate::task::{Priority, Status, Task};
+use chrono::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// How many days the completion chart covers, ending today.
+pub const DAYS: usize = 30;
+
+/// Figures for the stats view, over every level of the tasks and archive
+/// in the project being shown.
+pub struct Stats {
+    /// Tasks completed on each of the last `DAYS` days, oldest first.
+    pub completed: Vec<u64>,
+    /// Open tasks of high, medium and low priority.
+    pub open_by_priority: [usize; 3],
+    pub open: usize,
+    pub overdue: usize,
+    /// Open tasks per tag, most used first.
+    pub tags: Vec<(String, usize)>,
+    /// Days in a row with at least one task completed, up to today. A
+    /// streak is kept until a whole day passes without one.
+    pub current_streak: usize,
+    pub longest_streak: usize,
+}
+
+pub fn stats(app: &App) -> Stats {
+    let today = Local::now().date_naive();
+    let today_text = today.format("%Y-%m-%d").to_string();
+    let in_project = app.project_matcher(app.project_filter);
+    let tasks = app
+        .tasks
+        .iter()
+        .chain(&app.archive)
+        .filter(|task| in_project(task))
+        .flat_map(Task::with_subtasks);
+
+    let mut completed = vec![0; DAYS];
+    let mut days = HashSet::new();
+    let mut open_by_priority = [0; 3];
+    let mut open = 0;
+    let mut overdue = 0;
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    for task in tasks {
+        // Cancelled tasks are closed but not completed
+        if task.status == Status::Done {
+            if let Some(at) = task.completed_at {
+                let day = at.date_naive();
+                days.insert(day);
+                let ago = (today - day).num_days();
+                if (0..DAYS as i64).contains(&ago) {
+                    completed[DAYS - 1 - ago as usize] += 1;
+                }
+            }
+        }
+        if task.is_closed() {
+            continue;
+        }
+        open += 1;
+        open_by_priority[match task.priority {
+            Priority::High => 0,
+            Priority::Medium => 1,
+            Priority::Low => 2,
+        }] += 1;
+        if task.is_overdue(&today_text) {
+            overdue += 1;
+        }
+        for tag in &task.tags {
+            *tags.entry(tag).or_insert(0) += 1;
+        }
+    }
+
+    let mut tags: Vec<(String, usize)> = tags.into_iter().map(|(tag, count)| (tag.to_string(), count)).collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Stats {
+        completed,
+        open_by_priority,
+        open,
+        overdue,
+        tags,
+        current_streak: current_streak(&days, today),
+        longest_streak: longest_streak(&days),
+    }
+}
+
+fn current_streak(days: &HashSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = today;
+    if !days.contains(&day) {
+        day = day.pred_opt().unwrap();
+    }
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day = day.pred_opt().unwrap();
+    }
+    streak
+}
+
+fn longest_streak(days: &HashSet<NaiveDate>) -> usize {
+    let mut days: Vec<NaiveDate> = days.iter().copied().collect();
+    days.sort();
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        streak = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => streak + 1,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        previous = Some(day);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn days(texts: &[&str]) -> HashSet<NaiveDate> {
+        texts.iter().map(|text| day(text)).collect()
+    }
+
+    #[test]
+    fn a_streak_ending_today_or_yesterday_is_current() {
+        let today = day("2025-03-10");
+        let through_today = days(&["2025-03-08", "2025-03-09", "2025-03-10"]);
+        assert_eq!(current_streak(&through_today, today), 3);
+        let through_yesterday = days(&["2025-03-07", "2025-03-08", "2025-03-09"]);
+        assert_eq!(current_streak(&through_yesterday, today), 3);
+    }
+
+    #[test]
+    fn a_streak_is_broken_by_a_whole_day_without_completions() {
+        let today = day("2025-03-10");
+        assert_eq!(current_streak(&days(&["2025-03-07", "2025-03-08"]), today), 0);
+        assert_eq!(current_streak(&days(&["2025-03-06", "2025-03-08", "2025-03-09"]), today), 2);
+        assert_eq!(current_streak(&HashSet::new(), today), 0);
+    }
+
+    #[test]
+    fn the_longest_streak_is_found_anywhere() {
+        let completed = days(&["2025-01-01", "2025-01-02", "2025-01-03", "2025-02-10", "2025-02-11"]);
+        assert_eq!(longest_streak(&completed), 3);
+        assert_eq!(longest_streak(&days(&["2025-02-28", "2025-03-01"])), 2);
+        assert_eq!(longest_streak(&HashSet::new()), 0);
+    }
+}
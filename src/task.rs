@@ -1,35 +1,580 @@
+use crate::board::Grouping;
+use crate::sort::SortMode;
+use chrono::prelude::*;
+use chrono::Duration;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufReader, BufWriter};
 use std::path::Path;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
     Low,
     Medium,
     High,
 }
 
+impl Priority {
+    pub fn next(self) -> Priority {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::Low,
+        }
+    }
+}
+
+/// Where a task is in its workflow. Tasks saved before statuses existed
+/// only had `completed`, which `upgrade` turns into `Todo` or `Done`.
+/// Statuses are saved by name, so states removed from config.toml are kept
+/// as they were.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(from = "String", into = "String")]
+pub enum Status {
+    #[default]
+    Todo,
+    Doing,
+    /// Waiting on someone or something, see `Task::waiting_on`.
+    Waiting,
+    Done,
+    Cancelled,
+    /// A state from the `states` list in config.toml.
+    Custom(String),
+}
+
+impl Status {
+    pub const BUILT_IN: [Status; 5] = [
+        Status::Todo,
+        Status::Doing,
+        Status::Waiting,
+        Status::Done,
+        Status::Cancelled,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Status::Todo => "todo",
+            Status::Doing => "doing",
+            Status::Waiting => "waiting",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+            Status::Custom(name) => name,
+        }
+    }
+
+    /// A built-in status by its name or an alias such as `blocked`.
+    pub fn built_in(name: &str) -> Option<Status> {
+        match name.to_lowercase().as_str() {
+            "todo" => Some(Status::Todo),
+            "doing" | "in-progress" | "started" => Some(Status::Doing),
+            "waiting" | "blocked" => Some(Status::Waiting),
+            "done" | "completed" | "finished" => Some(Status::Done),
+            "cancelled" | "canceled" => Some(Status::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let name = self.name();
+        match self {
+            Status::Custom(_) => name.to_string(),
+            _ => name[..1].to_uppercase() + &name[1..],
+        }
+    }
+
+    /// The marker drawn in front of the task. Custom states get theirs from
+    /// config.toml.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Status::Todo => "❯",
+            Status::Doing => "◐",
+            Status::Waiting => "⏸",
+            Status::Done => "✔",
+            Status::Cancelled => "✘",
+            Status::Custom(_) => "•",
+        }
+    }
+
+    /// Done and cancelled tasks are closed; every other state is open.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
+    }
+}
+
+impl From<String> for Status {
+    fn from(name: String) -> Status {
+        Status::built_in(&name).unwrap_or(Status::Custom(name))
+    }
+}
+
+impl From<Status> for String {
+    fn from(status: Status) -> String {
+        status.name().to_string()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Task {
     pub id: usize,
     pub description: String,
-    pub completed: bool,
+    #[serde(default)]
+    pub status: Status,
+    /// Why a waiting task is waiting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waiting_on: Option<String>,
     pub priority: Priority,
     pub due_date: Option<String>,
-    pub sub_tasks: Box<Vec<Task>>,
+    pub sub_tasks: Vec<Task>,
     pub tags: Vec<String>,
+    /// Free-form multi-line text, lightly markdown-formatted.
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub modified_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Local>>,
+    /// The ID of the project a top-level task belongs to. Subtasks go with
+    /// their top-level task and ignore their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<usize>,
+    /// IDs of the tasks that have to be closed before this one can start.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<usize>,
+    /// Time worked on the task, oldest first. Only the last entry can still
+    /// be running.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub time_entries: Vec<TimeEntry>,
+    /// Pomodoro work phases finished on the task.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub pomodoros: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// A stretch of time spent on a task, recorded by its timer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeEntry {
+    pub start: DateTime<Local>,
+    /// Unset while the timer is running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Local>>,
+}
+
+impl Task {
+    /// Number of subtasks at every level below this task.
+    pub fn subtask_count(&self) -> usize {
+        self.sub_tasks
+            .iter()
+            .map(|task| 1 + task.subtask_count())
+            .sum()
+    }
+
+    /// This task followed by its subtasks at every level below it.
+    pub fn with_subtasks(&self) -> Vec<&Task> {
+        let mut tasks = vec![self];
+        for sub_task in &self.sub_tasks {
+            tasks.extend(sub_task.with_subtasks());
+        }
+        tasks
+    }
+
+    pub fn touch(&mut self) {
+        self.modified_at = Some(Local::now());
+    }
+
+    /// Applies a tag edit such as `+work -home urgent`: words starting with
+    /// `-` remove a tag, anything else adds one. The `#` prefix is optional.
+    pub fn edit_tags(&mut self, edit: &str) {
+        for word in edit.split_whitespace() {
+            let (remove, name) = match word.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, word.strip_prefix('+').unwrap_or(word)),
+            };
+            let name = name.trim_start_matches('#');
+            if name.is_empty() {
+                continue;
+            }
+            let tag = format!("#{}", name);
+            if remove {
+                self.tags.retain(|t| !t.eq_ignore_ascii_case(&tag));
+            } else if !self.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                self.tags.push(tag);
+            }
+        }
+        self.touch();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.status.is_closed()
+    }
+
+    pub fn set_completed(&mut self, completed: bool) {
+        self.set_status(if completed { Status::Done } else { Status::Todo });
+    }
+
+    /// `completed_at` records when the task was last closed, which also
+    /// stops its timer. Leaving the waiting state drops the reason.
+    pub fn set_status(&mut self, status: Status) {
+        if status.is_closed() != self.status.is_closed() {
+            self.completed_at = status.is_closed().then(Local::now);
+        }
+        if status.is_closed() {
+            self.stop_timer();
+        }
+        if status != Status::Waiting {
+            self.waiting_on = None;
+        }
+        self.status = status;
+        self.touch();
+    }
+
+    /// Whether the task's status matches `query`: a status name or alias,
+    /// or `open` or `closed`.
+    pub fn has_status(&self, query: &str) -> bool {
+        match query.to_lowercase().as_str() {
+            "open" | "incomplete" | "pending" => !self.is_closed(),
+            "closed" => self.is_closed(),
+            query => {
+                self.status.name().eq_ignore_ascii_case(query)
+                    || Status::built_in(query).is_some_and(|status| status == self.status)
+            }
+        }
+    }
+
+    pub fn is_timing(&self) -> bool {
+        self.time_entries.last().is_some_and(|entry| entry.end.is_none())
+    }
+
+    pub fn start_timer(&mut self) {
+        if !self.is_timing() {
+            self.time_entries.push(TimeEntry {
+                start: Local::now(),
+                end: None,
+            });
+        }
+    }
+
+    pub fn stop_timer(&mut self) {
+        if let Some(entry) = self.time_entries.last_mut().filter(|entry| entry.end.is_none()) {
+            entry.end = Some(Local::now());
+        }
+    }
+
+    /// Time recorded between `from` and `to`, counting a running timer up
+    /// to now. Subtasks keep their own time.
+    pub fn time_between(&self, from: DateTime<Local>, to: DateTime<Local>) -> Duration {
+        let now = Local::now();
+        self.time_entries
+            .iter()
+            .map(|entry| {
+                let start = entry.start.max(from);
+                let end = entry.end.unwrap_or(now).min(to);
+                (end - start).max(Duration::zero())
+            })
+            .sum()
+    }
+
+    /// All the time recorded on the task.
+    pub fn time_spent(&self) -> Duration {
+        self.time_entries
+            .iter()
+            .map(|entry| entry.end.unwrap_or_else(Local::now) - entry.start)
+            .sum()
+    }
+
+    /// The due date, if it is set and is a date rather than free text.
+    pub fn due_day(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.due_date.as_deref()?, "%Y-%m-%d").ok()
+    }
+
+    /// `today` is formatted as `%Y-%m-%d`, matching `due_date`. Due dates
+    /// kept as free text are never overdue.
+    pub fn is_overdue(&self, today: &str) -> bool {
+        let today = NaiveDate::parse_from_str(today, "%Y-%m-%d").ok();
+        !self.is_closed() && self.due_day().zip(today).is_some_and(|(due, today)| due < today)
+    }
+}
+
+/// View preferences stored in tasks.json next to the tasks themselves.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub sort_mode: SortMode,
+    /// Theme last picked at runtime; overrides the config file.
+    pub theme: Option<String>,
+    pub views: Vec<SavedView>,
+    /// How the board view groups its columns.
+    pub board: Grouping,
+}
+
+/// A named search, sort order and list, saved with `:view save`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedView {
+    pub name: String,
+    pub search: String,
+    pub sort_mode: SortMode,
+    /// Whether the view shows the archive rather than the task list.
+    pub archive: bool,
 }
 
-pub fn load_tasks<P: AsRef<Path>>(path: P) -> io::Result<Vec<Task>> {
+/// A list of tasks. Projects can nest under a parent project.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Project {
+    pub id: usize,
+    pub name: String,
+    /// A color name or hex code, as in theme files.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub parent: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TaskFile {
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Serialize)]
+struct TaskFileRef<'a> {
+    settings: &'a Settings,
+    projects: &'a [Project],
+    tasks: &'a [Task],
+}
+
+pub fn load_tasks<P: AsRef<Path>>(path: P) -> io::Result<TaskFile> {
+    File::open(path).map(BufReader::new).and_then(|reader| {
+        serde_json::from_reader(reader)
+            .map(upgrade)
+            .and_then(serde_json::from_value)
+            .map(|mut file: TaskFile| {
+                assign_unique_ids(&mut file.tasks);
+                file
+            })
+            .map_err(io::Error::other)
+    }).or_else(|_| Ok(TaskFile::default()))
+}
+
+pub fn save_tasks<P: AsRef<Path>>(
+    path: P,
+    tasks: &[Task],
+    projects: &[Project],
+    settings: &Settings,
+) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, &TaskFileRef { settings, projects, tasks })?;
+    Ok(())
+}
+
+/// archive.json holds completed tasks moved out of tasks.json.
+pub fn load_archive<P: AsRef<Path>>(path: P) -> io::Result<Vec<Task>> {
     File::open(path).map(BufReader::new).and_then(|reader| {
-        serde_json::from_reader(reader).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        serde_json::from_reader(reader)
+            .map(|mut tasks: serde_json::Value| {
+                upgrade_tasks(&mut tasks);
+                tasks
+            })
+            .and_then(serde_json::from_value)
+            .map_err(io::Error::other)
     }).or_else(|_| Ok(Vec::new()))
 }
 
-pub fn save_tasks<P: AsRef<Path>>(path: P, tasks: &[Task]) -> io::Result<()> {
+pub fn save_archive<P: AsRef<Path>>(path: P, tasks: &[Task]) -> io::Result<()> {
     let file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
     let writer = BufWriter::new(file);
     serde_json::to_writer_pretty(writer, tasks)?;
     Ok(())
 }
+
+/// The highest ID anywhere in the tree, subtasks included.
+pub fn max_id(tasks: &[Task]) -> usize {
+    tasks
+        .iter()
+        .map(|task| task.id.max(max_id(&task.sub_tasks)))
+        .max()
+        .unwrap_or(0)
+}
+
+/// The highest ID any task lists as a blocker.
+pub fn max_blocker_id(tasks: &[Task]) -> usize {
+    tasks
+        .iter()
+        .flat_map(|task| task.blocked_by.iter().copied().chain([max_blocker_id(&task.sub_tasks)]))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Subtasks used to be numbered from 1 within their parent. Tasks can now
+/// move between levels, so every ID in the tree has to be unique.
+fn assign_unique_ids(tasks: &mut [Task]) {
+    fn visit(tasks: &mut [Task], seen: &mut HashSet<usize>, next_id: &mut usize) {
+        for task in tasks {
+            if !seen.insert(task.id) {
+                *next_id += 1;
+                task.id = *next_id;
+                seen.insert(task.id);
+            }
+            visit(&mut task.sub_tasks, seen, next_id);
+        }
+    }
+    let mut next_id = max_id(tasks);
+    visit(tasks, &mut HashSet::new(), &mut next_id);
+}
+
+/// Older versions of tasks.json were a bare array of tasks.
+fn upgrade(value: serde_json::Value) -> serde_json::Value {
+    let mut value = match value {
+        serde_json::Value::Array(tasks) => serde_json::json!({ "tasks": tasks }),
+        other => other,
+    };
+    if let Some(tasks) = value.get_mut("tasks") {
+        upgrade_tasks(tasks);
+    }
+    value
+}
+
+/// Replaces the `completed` flag of older tasks with a status.
+fn upgrade_tasks(tasks: &mut serde_json::Value) {
+    let Some(tasks) = tasks.as_array_mut() else {
+        return;
+    };
+    for task in tasks.iter_mut().filter_map(|task| task.as_object_mut()) {
+        if let Some(completed) = task.remove("completed") {
+            let status = if completed.as_bool() == Some(true) { "done" } else { "todo" };
+            task.entry("status").or_insert(status.into());
+        }
+        if let Some(sub_tasks) = task.get_mut("sub_tasks") {
+            upgrade_tasks(sub_tasks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// tasks.json as written before statuses, projects and settings: a bare
+    /// array with a `completed` flag, and subtasks numbered from 1.
+    const LEGACY_TASKS: &str = r##"[
+        {
+            "id": 1,
+            "description": "Plan trip",
+            "completed": false,
+            "priority": "High",
+            "due_date": "2025-06-01",
+            "sub_tasks": [
+                {"id": 1, "description": "Book flights", "completed": true, "priority": "Medium",
+                 "due_date": null, "sub_tasks": [], "tags": []},
+                {"id": 2, "description": "Pack", "completed": false, "priority": "Low",
+                 "due_date": null, "sub_tasks": [], "tags": []}
+            ],
+            "tags": ["#travel"]
+        },
+        {"id": 2, "description": "Pay rent", "completed": true, "priority": "Medium",
+         "due_date": null, "sub_tasks": [], "tags": []}
+    ]"##;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("todo-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn legacy_task_files_load_with_statuses() {
+        let path = temp_path("legacy-tasks");
+        std::fs::write(&path, LEGACY_TASKS).unwrap();
+        let file = load_tasks(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(file.tasks.len(), 2);
+        let trip = &file.tasks[0];
+        assert_eq!(trip.status, Status::Todo);
+        assert_eq!(trip.priority, Priority::High);
+        assert_eq!(trip.due_date.as_deref(), Some("2025-06-01"));
+        assert_eq!(trip.tags, ["#travel"]);
+        assert_eq!(trip.sub_tasks[0].status, Status::Done);
+        assert_eq!(trip.sub_tasks[1].status, Status::Todo);
+        assert_eq!(file.tasks[1].status, Status::Done);
+        assert!(file.projects.is_empty());
+
+        // Subtask IDs that clashed with top-level ones are renumbered
+        let mut ids: Vec<usize> = file.tasks.iter().flat_map(Task::with_subtasks).map(|task| task.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn legacy_task_files_survive_saving_again() {
+        let path = temp_path("legacy-resave");
+        std::fs::write(&path, LEGACY_TASKS).unwrap();
+        let file = load_tasks(&path).unwrap();
+        save_tasks(&path, &file.tasks, &file.projects, &file.settings).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let again = load_tasks(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!text.contains("\"completed\""));
+        let statuses = |file: &TaskFile| -> Vec<(usize, Status)> {
+            file.tasks.iter().flat_map(Task::with_subtasks).map(|task| (task.id, task.status.clone())).collect()
+        };
+        assert_eq!(statuses(&again), statuses(&file));
+    }
+
+    #[test]
+    fn legacy_archives_load_with_statuses() {
+        let path = temp_path("legacy-archive");
+        std::fs::write(&path, LEGACY_TASKS).unwrap();
+        let archive = load_archive(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(archive[1].status, Status::Done);
+        assert_eq!(archive[0].sub_tasks[0].status, Status::Done);
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 3, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn time_between_clips_entries_to_the_range() {
+        let mut task: Task = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "description": "Write report",
+            "priority": "Medium",
+            "due_date": null,
+            "sub_tasks": [],
+            "tags": [],
+        }))
+        .unwrap();
+        task.time_entries = vec![
+            // Crosses midnight into the 10th
+            TimeEntry { start: at(9, 23, 0), end: Some(at(10, 1, 0)) },
+            TimeEntry { start: at(10, 9, 0), end: Some(at(10, 9, 45)) },
+            TimeEntry { start: at(12, 8, 0), end: Some(at(12, 8, 30)) },
+        ];
+        assert_eq!(task.time_between(at(10, 0, 0), at(11, 0, 0)), Duration::minutes(105));
+        assert_eq!(task.time_between(at(9, 0, 0), at(13, 0, 0)), Duration::minutes(195));
+        assert_eq!(task.time_between(at(11, 0, 0), at(12, 0, 0)), Duration::zero());
+        assert_eq!(task.time_spent(), Duration::minutes(195));
+
+        // A running timer counts up to now
+        task.time_entries.push(TimeEntry { start: Local::now() - Duration::minutes(10), end: None });
+        let running = task.time_between(Local::now() - Duration::hours(1), Local::now() + Duration::hours(1));
+        assert!(running >= Duration::minutes(10) && running < Duration::minutes(11));
+    }
+
+    #[test]
+    fn a_status_already_set_wins_over_the_completed_flag() {
+        let mut tasks = serde_json::json!([{"id": 1, "completed": true, "status": "waiting"}]);
+        upgrade_tasks(&mut tasks);
+        assert_eq!(tasks, serde_json::json!([{"id": 1, "status": "waiting"}]));
+    }
+}
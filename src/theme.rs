@@ -0,0 +1,343 @@
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The colors every render function draws with. Built-in themes are the
+/// four Catppuccin flavours plus a high-contrast theme; more can be loaded
+/// from TOML files in the `themes` directory.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    /// Markers, keys and tags.
+    pub accent: Color,
+    /// Titles and the selected row.
+    pub title: Color,
+    pub text: Color,
+    pub subtext: Color,
+    /// Completed tasks and other de-emphasised text.
+    pub muted: Color,
+    pub border: Color,
+    pub selection: Color,
+    pub background: Color,
+    /// High priority and overdue dates.
+    pub danger: Color,
+    /// Medium priority.
+    pub warning: Color,
+    /// Low priority.
+    pub success: Color,
+}
+
+/// How many colors the terminal can show.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Detect from the environment.
+    #[default]
+    Auto,
+    #[serde(alias = "24bit")]
+    TrueColor,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
+const fn hex(rgb: u32) -> Color {
+    Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+impl Theme {
+    pub fn mocha() -> Theme {
+        Theme {
+            name: "mocha".to_string(),
+            accent: hex(0xcba6f7),
+            title: hex(0xb4befe),
+            text: hex(0xcdd6f4),
+            subtext: hex(0xbac2de),
+            muted: hex(0x585b70),
+            border: hex(0x45475a),
+            selection: hex(0x313244),
+            background: hex(0x11111b),
+            danger: hex(0xf38ba8),
+            warning: hex(0xfab387),
+            success: hex(0xa6e3a1),
+        }
+    }
+
+    pub fn macchiato() -> Theme {
+        Theme {
+            name: "macchiato".to_string(),
+            accent: hex(0xc6a0f6),
+            title: hex(0xb7bdf8),
+            text: hex(0xcad3f5),
+            subtext: hex(0xb8c0e0),
+            muted: hex(0x5b6078),
+            border: hex(0x494d64),
+            selection: hex(0x363a4f),
+            background: hex(0x181926),
+            danger: hex(0xed8796),
+            warning: hex(0xf5a97f),
+            success: hex(0xa6da95),
+        }
+    }
+
+    pub fn frappe() -> Theme {
+        Theme {
+            name: "frappe".to_string(),
+            accent: hex(0xca9ee6),
+            title: hex(0xbabbf1),
+            text: hex(0xc6d0f5),
+            subtext: hex(0xb5bfe2),
+            muted: hex(0x626880),
+            border: hex(0x51576d),
+            selection: hex(0x414559),
+            background: hex(0x232634),
+            danger: hex(0xe78284),
+            warning: hex(0xef9f76),
+            success: hex(0xa6d189),
+        }
+    }
+
+    pub fn latte() -> Theme {
+        Theme {
+            name: "latte".to_string(),
+            accent: hex(0x8839ef),
+            title: hex(0x7287fd),
+            text: hex(0x4c4f69),
+            subtext: hex(0x5c5f77),
+            muted: hex(0xacb0be),
+            border: hex(0xbcc0cc),
+            selection: hex(0xccd0da),
+            background: hex(0xdce0e8),
+            danger: hex(0xd20f39),
+            warning: hex(0xfe640b),
+            success: hex(0x40a02b),
+        }
+    }
+
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: "high-contrast".to_string(),
+            accent: hex(0xffff00),
+            title: hex(0x00ffff),
+            text: hex(0xffffff),
+            subtext: hex(0xffffff),
+            muted: hex(0xa0a0a0),
+            border: hex(0xffffff),
+            selection: hex(0x0000c0),
+            background: hex(0x000000),
+            danger: hex(0xff0000),
+            warning: hex(0xffff00),
+            success: hex(0x00ff00),
+        }
+    }
+
+    pub fn built_in() -> Vec<Theme> {
+        vec![
+            Theme::latte(),
+            Theme::frappe(),
+            Theme::macchiato(),
+            Theme::mocha(),
+            Theme::high_contrast(),
+        ]
+    }
+
+    fn colors_mut(&mut self) -> [&mut Color; 11] {
+        [
+            &mut self.accent,
+            &mut self.title,
+            &mut self.text,
+            &mut self.subtext,
+            &mut self.muted,
+            &mut self.border,
+            &mut self.selection,
+            &mut self.background,
+            &mut self.danger,
+            &mut self.warning,
+            &mut self.success,
+        ]
+    }
+
+    /// Converts RGB colors to the nearest color the terminal can show.
+    pub fn downsample(mut self, mode: ColorMode) -> Theme {
+        let convert: fn(u8, u8, u8) -> Color = match mode.resolve() {
+            ColorMode::Ansi256 => rgb_to_256,
+            ColorMode::Ansi16 => rgb_to_16,
+            _ => return self,
+        };
+        for color in self.colors_mut() {
+            if let Color::Rgb(r, g, b) = *color {
+                *color = convert(r, g, b);
+            }
+        }
+        self
+    }
+}
+
+impl ColorMode {
+    /// Resolves `Auto` by looking at the terminal environment.
+    pub fn resolve(self) -> ColorMode {
+        if self != ColorMode::Auto {
+            return self;
+        }
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" || std::env::var_os("WT_SESSION").is_some() {
+            ColorMode::TrueColor
+        } else if term.contains("256color") {
+            ColorMode::Ansi256
+        } else if term.is_empty() && cfg!(windows) {
+            // Modern Windows consoles handle RGB but set neither variable
+            ColorMode::TrueColor
+        } else {
+            ColorMode::Ansi16
+        }
+    }
+}
+
+/// A theme file: a base theme plus overrides for any of its colors, each
+/// given as a name (`red`, `lightblue`), a hex code (`#cba6f7`) or a
+/// 256-color index.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ThemeFile {
+    name: Option<String>,
+    base: Option<String>,
+    accent: Option<String>,
+    title: Option<String>,
+    text: Option<String>,
+    subtext: Option<String>,
+    muted: Option<String>,
+    border: Option<String>,
+    selection: Option<String>,
+    background: Option<String>,
+    danger: Option<String>,
+    warning: Option<String>,
+    success: Option<String>,
+}
+
+/// Loads every `*.toml` theme in `dir`. A theme is named after its file
+/// unless it sets `name`. Files that fail to parse are reported by name.
+pub fn load_themes<P: AsRef<Path>>(dir: P) -> (Vec<Theme>, Vec<String>) {
+    let mut themes = Vec::new();
+    let mut errors = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (themes, errors);
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        match load_theme(&path) {
+            Ok(theme) => themes.push(theme),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    (themes, errors)
+}
+
+fn load_theme(path: &Path) -> io::Result<Theme> {
+    let file: ThemeFile = toml::from_str(&fs::read_to_string(path)?).map_err(io::Error::other)?;
+    let base = file.base.as_deref().unwrap_or("mocha");
+    let mut theme = Theme::built_in()
+        .into_iter()
+        .find(|theme| theme.name == base)
+        .ok_or_else(|| io::Error::other(format!("unknown base theme `{}`", base)))?;
+    theme.name = file.name.clone().unwrap_or_else(|| {
+        path.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned())
+    });
+
+    let overrides = [
+        file.accent,
+        file.title,
+        file.text,
+        file.subtext,
+        file.muted,
+        file.border,
+        file.selection,
+        file.background,
+        file.danger,
+        file.warning,
+        file.success,
+    ];
+    for (color, value) in theme.colors_mut().into_iter().zip(overrides) {
+        if let Some(value) = value {
+            *color = Color::from_str(&value)
+                .map_err(|_| io::Error::other(format!("invalid color `{}`", value)))?;
+        }
+    }
+    Ok(theme)
+}
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> Color {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    // Indices 232-255 are a grey ramp from 8 to 238 in steps of 10
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = ((average.saturating_sub(8) + 5) / 10).min(23) as u8;
+    let grey = 8 + 10 * grey_index;
+
+    if distance((grey, grey, grey)) < distance(cube) {
+        Color::Indexed(232 + grey_index)
+    } else {
+        Color::Indexed(16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8)
+    }
+}
+
+/// Picks a basic ANSI color by hue rather than raw distance, so pastel
+/// palettes keep their reds, yellows and greens apart.
+fn rgb_to_16(r: u8, g: u8, b: u8) -> Color {
+    let max = r.max(g).max(b) as f32;
+    let min = r.min(g).min(b) as f32;
+    let chroma = max - min;
+    let bright = max > 160.0;
+
+    if chroma < 40.0 {
+        return match max as u8 {
+            0..=63 => Color::Black,
+            64..=159 => Color::DarkGray,
+            160..=223 => Color::Gray,
+            _ => Color::White,
+        };
+    }
+
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let hue = if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+
+    match (hue as u16, bright) {
+        (0..=19 | 330..=360, false) => Color::Red,
+        (0..=19 | 330..=360, true) => Color::LightRed,
+        (20..=74, false) => Color::Yellow,
+        (20..=74, true) => Color::LightYellow,
+        (75..=164, false) => Color::Green,
+        (75..=164, true) => Color::LightGreen,
+        (165..=199, false) => Color::Cyan,
+        (165..=199, true) => Color::LightCyan,
+        (200..=274, false) => Color::Blue,
+        (200..=274, true) => Color::LightBlue,
+        (_, false) => Color::Magenta,
+        (_, true) => Color::LightMagenta,
+    }
+}
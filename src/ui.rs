@@ -1,31 +1,32 @@
-use crate::app::{App, AppMode};
-use crate::task::Priority;
+use crate::agenda::{self, AgendaGroup};
+use crate::app::{App, AppMode, Focus, PendingAction, ProjectFilter, View};
+use crate::board;
+use crate::keymap::{Action, KeyContext};
+use crate::picker::{picker_items, PickerItem};
+use crate::pomodoro::{format_remaining, Phase};
+use crate::report::format_duration;
+use crate::sort::SortMode;
+use crate::stats::{self, Stats};
+use crate::task::{Priority, Project, Status, Task};
+use crate::theme::Theme;
 use chrono::prelude::*;
 use ratatui::{
-    backend::Backend,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    widgets::{
+        block::Title,
+        calendar::{CalendarEventStore, Monthly},
+        Bar, BarChart, BarGroup, Block, Borders, Clear, LineGauge, List, ListItem, ListState, Paragraph, Wrap,
+    },
     Frame,
 };
+use std::str::FromStr;
 
-// Catppuccin Mocha color palette
-const MAUVE: Color = Color::Rgb(203, 166, 247);
-const LAVENDER: Color = Color::Rgb(180, 190, 254);
-const TEXT: Color = Color::Rgb(205, 214, 244);
-const SUBTEXT1: Color = Color::Rgb(186, 194, 222);
-const SURFACE2: Color = Color::Rgb(88, 91, 112);
-const SURFACE1: Color = Color::Rgb(69, 71, 90);
-const SURFACE0: Color = Color::Rgb(49, 50, 68);
-const CRUST: Color = Color::Rgb(17, 17, 27);
-const RED: Color = Color::Rgb(243, 139, 168);
-const YELLOW: Color = Color::Rgb(250, 179, 135);
-const GREEN: Color = Color::Rgb(166, 227, 161);
-
-pub fn ui<B: Backend>(f: &mut Frame, app: &mut App) {
+pub fn ui(f: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
     // Create a global background
-    let background = Block::default().style(Style::default().bg(CRUST));
+    let background = Block::default().style(Style::default().bg(theme.background));
     f.render_widget(background, f.size());
 
     // Create a centered viewport based on the margin/zoom level
@@ -49,224 +50,1351 @@ pub fn ui<B: Backend>(f: &mut Frame, app: &mut App) {
 
     let viewport = horizontal_chunks[1];
 
+    if app.focus != Focus::Off {
+        render_focus(f, app, viewport);
+    } else {
+        render_main(f, app, viewport);
+    }
+
+    app.areas.popup = match app.mode {
+        AppMode::Insert => Some(render_input_popup(f, app)),
+        AppMode::DateInput => Some(render_date_input_popup(f, app)),
+        AppMode::Search => Some(render_search_popup(f, app)),
+        AppMode::TagInput => Some(render_tag_input_popup(f, app)),
+        AppMode::Notes => Some(render_notes_popup(f, app)),
+        AppMode::Confirm(action) => Some(render_confirm_popup(f, app, action)),
+        AppMode::Help(context) => Some(render_help_popup(f, app, context)),
+        AppMode::Command => Some(render_command_popup(f, app)),
+        AppMode::Picker(context) => Some(render_picker_popup(f, app, context)),
+        AppMode::Normal | AppMode::Select => None,
+    };
+}
+
+fn render_main(f: &mut Frame, app: &mut App, viewport: Rect) {
     // Render the application within the calculated viewport
     let app_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
         .split(viewport);
 
-    render_tasks(f, app, app_chunks[0]);
-    render_footer(f, app_chunks[1]);
+    app.areas.sidebar.clear();
+    let main_area = if app.show_sidebar {
+        let sidebar_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(26), Constraint::Min(0)].as_ref())
+            .split(app_chunks[0]);
+        render_sidebar(f, app, sidebar_chunks[0]);
+        sidebar_chunks[1]
+    } else {
+        app_chunks[0]
+    };
 
-    if let AppMode::Insert = app.mode {
-        render_input_popup(f, app);
+    if app.show_details {
+        let task_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(main_area);
+        render_tasks(f, app, task_chunks[0]);
+        render_details(f, app, task_chunks[1]);
+    } else {
+        render_tasks(f, app, main_area);
     }
-    if let AppMode::DateInput = app.mode {
-        render_date_input_popup(f, app);
+    render_footer(f, app, app_chunks[1]);
+}
+
+/// Lists All, Inbox and every project, nested projects indented under
+/// their parents, with counts of open tasks.
+fn render_sidebar(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.theme;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Projects ")
+        .border_style(Style::default().fg(theme.border))
+        .title_style(Style::default().fg(theme.title));
+    let inner = block.inner(area);
+
+    let mut entries = vec![
+        (ProjectFilter::All, 0, "All".to_string(), theme.accent),
+        (ProjectFilter::Inbox, 0, "Inbox".to_string(), theme.accent),
+    ];
+    entries.extend(app.project_tree().into_iter().map(|(depth, project)| {
+        (
+            ProjectFilter::Project(project.id),
+            depth,
+            project.name.clone(),
+            project_color(theme, project),
+        )
+    }));
+
+    let mut lines = Vec::new();
+    let mut clickable = Vec::new();
+    for (i, (filter, depth, name, color)) in entries.into_iter().enumerate() {
+        let count = app.open_count(filter).to_string();
+        let indent = "  ".repeat(depth);
+        // Indent, bullet and space, then the name, then the count on the right
+        let width = (inner.width as usize).saturating_sub(indent.len() + 3 + count.len());
+        let name: String = name.chars().take(width).collect();
+        let padding = " ".repeat(width - name.chars().count() + 1);
+        let style = if filter == app.project_filter {
+            Style::default().bg(theme.selection).fg(theme.title).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.text)
+        };
+        lines.push(
+            Line::from(vec![
+                Span::raw(indent),
+                Span::styled("● ", Style::default().fg(color)),
+                Span::raw(name + &padding),
+                Span::styled(count, Style::default().fg(theme.subtext)),
+            ])
+            .style(style),
+        );
+        if (i as u16) < inner.height {
+            clickable.push((Rect { y: inner.y + i as u16, height: 1, ..inner }, filter));
+        }
     }
-    if let AppMode::Search = app.mode {
-        render_search_popup(f, app);
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+    app.areas.sidebar = clickable;
+}
+
+/// A project's own color, or the accent color if it has none or an invalid
+/// one.
+fn project_color(theme: &Theme, project: &Project) -> Color {
+    project
+        .color
+        .as_deref()
+        .and_then(|color| Color::from_str(color).ok())
+        .unwrap_or(theme.accent)
+}
+
+/// Focus mode drops the footer and details pane. A single focused task gets
+/// a large centered area of its own.
+fn render_focus(f: &mut Frame, app: &mut App, viewport: Rect) {
+    app.areas.footer_keys.clear();
+    app.areas.sidebar.clear();
+    let viewport = match app.focus {
+        Focus::Single(_) => centered_rect(70, 80, f.size()),
+        _ => viewport,
+    };
+    // Status messages still need somewhere to go without the footer
+    let status_height = u16::from(app.status_message.is_some());
+    let focus_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(status_height)].as_ref())
+        .split(viewport);
+
+    render_tasks(f, app, focus_chunks[0]);
+    if let Some(message) = &app.status_message {
+        let status = Paragraph::new(message.as_str()).style(Style::default().fg(app.theme.subtext));
+        f.render_widget(status, focus_chunks[1]);
     }
 }
 
 fn render_tasks(f: &mut Frame, app: &mut App, area: Rect) {
+    app.areas.agenda.clear();
+    app.areas.calendar.clear();
+    if app.view == View::Board {
+        return render_board(f, app, area);
+    }
+    app.areas.board.clear();
+    if app.view == View::Agenda {
+        return render_agenda(f, app, area);
+    }
+    if app.view == View::Stats {
+        return render_stats(f, app, area);
+    }
+    let theme = &app.theme;
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let items: Vec<ListItem> = app
+        .visible_rows()
+        .iter()
+        .filter_map(|row| {
+            app.row_task(row).map(|task| {
+                // Archived matches mixed into a task search get a marker
+                let archived = row.archived && app.view == View::Tasks;
+                // Name the project unless it is the one being shown
+                let project = app
+                    .row_project(row)
+                    .filter(|p| row.depth() == 0 && app.project_filter != ProjectFilter::Project(p.id))
+                    .map(|p| (p.name.clone(), project_color(theme, p)));
+                // Next actions are a flat list
+                let depth = if app.view == View::Next { 0 } else { row.depth() };
+                let item = task_item(app, task, depth, archived, project, &today);
+                if !row.archived && app.marked.contains(&task.id) {
+                    item.bg(theme.border)
+                } else {
+                    item
+                }
+            })
+        })
+        .collect();
+
+    let title = list_title(app);
+
+    let block = if app.focus == Focus::Off {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(Style::default().fg(theme.border))
+            .title_style(Style::default().fg(theme.title))
+    } else {
+        Block::default()
+    };
+
+    app.areas.list = block.inner(area);
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(theme.selection)
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(" ➤ ");
+
+    f.render_stateful_widget(list, area, &mut app.state);
+}
+
+/// The title of the list or board: view, project, search, marks and sort.
+fn list_title(app: &App) -> String {
+    let name = match app.view {
+        View::Tasks => "To-Do",
+        View::Board => "Board",
+        View::Next => "Next actions",
+        View::Agenda => "Agenda",
+        View::Stats => "Stats",
+        View::Archive => "Archive",
+    };
+    let name = match app.project_filter {
+        ProjectFilter::All => name.to_string(),
+        filter => format!("{}: {}", name, app.project_filter_name(filter)),
+    };
+    let title = match app.mode {
+        AppMode::Search if !app.search_input.is_empty() => {
+            format!(" {} (Search: {}) ", name, app.search_input)
+        }
+        AppMode::Search => format!(" {} (Search Mode) ", name),
+        _ if !app.search_input.is_empty() => format!(" {} (Filter: {}) ", name, app.search_input),
+        _ => format!(" {} ", name),
+    };
+    let title = match app.mode {
+        AppMode::Select => format!("{}[{} marked] ", title, app.marked.len()),
+        _ => title,
+    };
+    let title = match app.sort_mode {
+        // The stats have no list to sort
+        _ if app.view == View::Stats => title,
+        SortMode::Manual => title,
+        mode => format!("{}[sort: {}] ", title, mode.label()),
+    };
+    match app.view {
+        View::Board => format!("{}[by {}] ", title, app.board_grouping.name()),
+        View::Agenda => match app.agenda_day {
+            Some(day) => format!("{}[{}] ", title, day.format("%a %-d %b")),
+            None => title,
+        },
+        _ => title,
+    }
+}
+
+/// The month calendar beside the agenda list, which groups tasks under
+/// headings by when they are due.
+fn render_agenda(f: &mut Frame, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(23), Constraint::Min(0)].as_ref())
+        .split(area);
+    render_calendar(f, app, chunks[0]);
+
+    let theme = &app.theme;
+    let today = Local::now().date_naive();
+    let today_text = today.format("%Y-%m-%d").to_string();
     let mut items = Vec::new();
-    let displayed_tasks = app.get_displayed_tasks();
-    for task in displayed_tasks.iter() {
-        let (style, symbol) = if task.completed {
-            (Style::default().fg(SURFACE2).add_modifier(Modifier::CROSSED_OUT), " ✔ ")
-        } else {
-            (Style::default().fg(TEXT), " ❯ ")
+    let mut lines = Vec::new();
+    let mut group = None;
+    for (i, row) in app.visible_rows().iter().enumerate() {
+        let Some(task) = app.row_task(row) else {
+            continue;
         };
-        let priority_style = Style::default().fg(match task.priority {
-            Priority::High => RED,
-            Priority::Medium => YELLOW,
-            Priority::Low => GREEN,
-        });
-        let priority_symbol = match task.priority {
-            Priority::High => " ▲",
-            Priority::Medium => " ●",
-            Priority::Low => " ▼",
+        let due_group = agenda::due_date(task).map(|due| AgendaGroup::of(due, today));
+        if due_group != group {
+            group = due_group;
+            let title = group.map_or("", AgendaGroup::title);
+            let color = if group == Some(AgendaGroup::Overdue) { theme.danger } else { theme.title };
+            items.push(ListItem::new(Line::styled(
+                format!(" {}", title),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )));
+            lines.push(None);
+        }
+        let project = app
+            .row_project(row)
+            .filter(|p| app.project_filter != ProjectFilter::Project(p.id))
+            .map(|p| (p.name.clone(), project_color(theme, p)));
+        let item = task_item(app, task, 0, false, project, &today_text);
+        items.push(if app.marked.contains(&task.id) { item.bg(theme.border) } else { item });
+        lines.push(Some(i));
+    }
+
+    let block = if app.focus == Focus::Off {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(list_title(app))
+            .border_style(Style::default().fg(theme.border))
+            .title_style(Style::default().fg(theme.title))
+    } else {
+        Block::default()
+    };
+    app.areas.list = block.inner(chunks[1]);
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(theme.selection)
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(" ➤ ");
+    // Headings take lines of their own, so the selected row is drawn lower
+    let mut state = ListState::default();
+    state.select(lines.iter().position(|&line| line.is_some() && line == app.state.selected()));
+    f.render_stateful_widget(list, chunks[1], &mut state);
+    app.areas.agenda = lines[state.offset().min(lines.len())..].to_vec();
+}
+
+/// Completions over the last days, open tasks by priority and tag, and
+/// completion streaks.
+fn render_stats(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.theme;
+    let stats = stats::stats(app);
+    let block = if app.focus == Focus::Off {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(list_title(app))
+            .border_style(Style::default().fg(theme.border))
+            .title_style(Style::default().fg(theme.title))
+    } else {
+        Block::default()
+    };
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    // Nothing to click on
+    app.areas.list = Rect::default();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(10), Constraint::Length(7), Constraint::Min(0)].as_ref())
+        .split(inner);
+    render_completions(f, theme, &stats, chunks[0]);
+
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(chunks[1]);
+    let block = stats_block(theme, format!(" Open tasks: {} ", stats.open));
+    let gauges = block.inner(middle[0]);
+    f.render_widget(block, middle[0]);
+    let priorities = [
+        ("High", theme.danger),
+        ("Medium", theme.warning),
+        ("Low", theme.success),
+    ];
+    for (i, ((label, color), count)) in priorities.into_iter().zip(stats.open_by_priority).enumerate() {
+        if i as u16 >= gauges.height {
+            break;
+        }
+        let gauge = LineGauge::default()
+            .ratio(if stats.open == 0 { 0.0 } else { count as f64 / stats.open as f64 })
+            .label(format!("{:<7}{:>4} ", label, count))
+            .style(Style::default().fg(theme.text))
+            .gauge_style(Style::default().fg(color).bg(theme.muted));
+        f.render_widget(gauge, Rect { y: gauges.y + i as u16, height: 1, ..gauges });
+    }
+    if gauges.height > 4 {
+        let color = if stats.overdue > 0 { theme.danger } else { theme.subtext };
+        let overdue = Paragraph::new(format!("Overdue{:>5}", stats.overdue)).style(Style::default().fg(color));
+        f.render_widget(overdue, Rect { y: gauges.y + 4, height: 1, ..gauges });
+    }
+
+    let day = |n: usize| if n == 1 { "day" } else { "days" };
+    let streaks = Paragraph::new(vec![
+        Line::from(vec![
+            Span::styled("Current  ", Style::default().fg(theme.subtext)),
+            Span::styled(
+                format!("{} {}", stats.current_streak, day(stats.current_streak)),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Longest  ", Style::default().fg(theme.subtext)),
+            Span::styled(
+                format!("{} {}", stats.longest_streak, day(stats.longest_streak)),
+                Style::default().fg(theme.text),
+            ),
+        ]),
+    ])
+    .block(stats_block(theme, " Streaks ".to_string()));
+    f.render_widget(streaks, middle[1]);
+
+    let bars: Vec<Bar> = stats
+        .tags
+        .iter()
+        .map(|(tag, count)| Bar::default().label(Line::from(tag.clone())).value(*count as u64))
+        .collect();
+    let tags = BarChart::default()
+        .block(stats_block(theme, " Open tasks by tag ".to_string()))
+        .direction(Direction::Horizontal)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(1)
+        .bar_gap(0)
+        .bar_style(Style::default().fg(theme.accent))
+        .value_style(Style::default().fg(theme.background).bg(theme.accent))
+        .label_style(Style::default().fg(theme.text));
+    f.render_widget(tags, chunks[2]);
+}
+
+fn stats_block(theme: &Theme, title: String) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .border_style(Style::default().fg(theme.border))
+        .title_style(Style::default().fg(theme.title))
+}
+
+/// A bar per day, widened to fill the chart. Day numbers go under the bars
+/// once they are wide enough.
+fn render_completions(f: &mut Frame, theme: &Theme, stats: &Stats, area: Rect) {
+    let total: u64 = stats.completed.iter().sum();
+    let block = stats_block(theme, format!(" Completed in the last {} days: {} ", stats::DAYS, total));
+    let width = block.inner(area).width / stats::DAYS as u16;
+    let bar_width = width.saturating_sub(1).max(1);
+    let today = Local::now().date_naive();
+    let bars: Vec<Bar> = stats
+        .completed
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| {
+            let day = today - chrono::Duration::days((stats::DAYS - 1 - i) as i64);
+            let label = if bar_width >= 2 { day.format("%-d").to_string() } else { String::new() };
+            let text = if count == 0 { String::new() } else { count.to_string() };
+            Bar::default().value(count).text_value(text).label(Line::from(label))
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(block)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width)
+        .bar_gap(u16::from(width > 1))
+        .bar_style(Style::default().fg(theme.success))
+        .value_style(Style::default().fg(theme.background).bg(theme.success))
+        .label_style(Style::default().fg(theme.subtext));
+    f.render_widget(chart, area);
+}
+
+/// The agenda's month grid, with days that have tasks due colored by how
+/// many, and the count for each of those days listed underneath.
+fn render_calendar(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.theme;
+    let today = Local::now().date_naive();
+    let counts = app.agenda_counts();
+    let month = app.agenda_month;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(10), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let mut events = CalendarEventStore::default();
+    let mut days: Vec<NaiveDate> = counts.keys().copied().chain([today]).chain(app.agenda_day).collect();
+    days.sort();
+    days.dedup();
+    for day in days {
+        let mut style = match counts.get(&day).copied().unwrap_or(0) {
+            0 => Style::default(),
+            1 => Style::default().fg(theme.accent),
+            2 => Style::default().fg(theme.warning),
+            _ => Style::default().fg(theme.danger).add_modifier(Modifier::BOLD),
         };
+        if day == today {
+            style = style.add_modifier(Modifier::UNDERLINED | Modifier::BOLD);
+        }
+        if Some(day) == app.agenda_day {
+            style = style.bg(theme.selection);
+        }
+        if let Some(date) = agenda::calendar_date(day) {
+            events.add(date, style);
+        }
+    }
 
-        let mut spans = vec![
-            Span::styled(symbol, Style::default().fg(MAUVE)),
-            Span::raw(task.description.clone()),
-            Span::styled(priority_symbol, priority_style),
-        ];
-
-        if let Some(due_date) = &task.due_date {
-            let due_date_style = if Local::now().format("%Y-%m-%d").to_string() > *due_date {
-                Style::default().fg(RED)
-            } else {
-                Style::default().fg(SUBTEXT1)
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border));
+    let grid = block.inner(chunks[0]);
+    if let Some(date) = agenda::calendar_date(month) {
+        let calendar = Monthly::new(date, events)
+            .block(block)
+            .show_month_header(Style::default().fg(theme.title).add_modifier(Modifier::BOLD))
+            .show_weekdays_header(Style::default().fg(theme.subtext))
+            .default_style(Style::default().fg(theme.muted));
+        f.render_widget(calendar, chunks[0]);
+    }
+
+    // Below the month and weekday headers, each day is two characters
+    // after a one-character gutter
+    let mut clickable = Vec::new();
+    for week in 0..6 {
+        for column in 0..7 {
+            let Some(day) = agenda::grid_date(month, week, column) else {
+                continue;
             };
-            spans.push(Span::styled(format!(" (due: {})", due_date), due_date_style));
+            let y = grid.y + 2 + week as u16;
+            if day.month() == month.month() && y < grid.bottom() {
+                let x = grid.x + 1 + 3 * column as u16;
+                clickable.push((Rect { x, y, width: 2, height: 1 }, day));
+            }
+        }
+    }
+    app.areas.calendar = clickable;
+
+    let mut due: Vec<(NaiveDate, usize)> = counts
+        .into_iter()
+        .filter(|(day, _)| day.year() == month.year() && day.month() == month.month())
+        .collect();
+    due.sort();
+    let lines: Vec<Line> = due
+        .into_iter()
+        .map(|(day, count)| {
+            let color = if day < today { theme.danger } else { theme.text };
+            Line::from(vec![
+                Span::styled(format!(" {:<10}", day.format("%a %-d")), Style::default().fg(color)),
+                Span::styled(
+                    format!("{} task{}", count, if count == 1 { "" } else { "s" }),
+                    Style::default().fg(theme.subtext),
+                ),
+            ])
+        })
+        .collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Due this month ")
+        .border_style(Style::default().fg(theme.border))
+        .title_style(Style::default().fg(theme.title));
+    f.render_widget(Paragraph::new(lines).block(block), chunks[1]);
+}
+
+/// Top-level tasks as cards in one column per status, priority or tag.
+fn render_board(f: &mut Frame, app: &mut App, area: Rect) {
+    app.sync_board();
+    let theme = &app.theme;
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let rows = app.visible_rows();
+    let columns = board::columns(app);
+
+    let block = if app.focus == Focus::Off {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(list_title(app))
+            .border_style(Style::default().fg(theme.border))
+            .title_style(Style::default().fg(theme.title))
+    } else {
+        Block::default()
+    };
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let constraints = vec![Constraint::Ratio(1, columns.len().max(1) as u32); columns.len()];
+    let column_areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(inner);
+
+    let mut board_areas = Vec::new();
+    for (i, (column, &column_area)) in columns.iter().zip(column_areas.iter()).enumerate() {
+        let current = i == app.board_column;
+        let items: Vec<ListItem> = column
+            .rows
+            .iter()
+            .filter_map(|&r| app.row_task(&rows[r]))
+            .map(|task| task_item(app, task, 0, false, None, &today))
+            .collect();
+        let border = if current { theme.accent } else { theme.border };
+        let column_block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ({}) ", column.group.title(), column.rows.len()))
+            .border_style(Style::default().fg(border))
+            .title_style(Style::default().fg(theme.title));
+        let cards = column_block.inner(column_area);
+        let list = List::new(items).block(column_block).highlight_style(
+            Style::default()
+                .bg(theme.selection)
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        );
+        let mut state = ListState::default();
+        if current {
+            state.select(column.rows.iter().position(|&r| Some(r) == app.state.selected()));
         }
+        f.render_stateful_widget(list, column_area, &mut state);
+        board_areas.push((cards, column.rows[state.offset().min(column.rows.len())..].to_vec()));
+    }
 
-        if !task.tags.is_empty() {
+    // Clicks go through the board's columns rather than the list
+    app.areas.list = Rect::default();
+    app.areas.board = board_areas;
+}
+
+fn task_item(
+    app: &App,
+    task: &Task,
+    depth: usize,
+    archived: bool,
+    project: Option<(String, Color)>,
+    today: &str,
+) -> ListItem<'static> {
+    let theme = &app.theme;
+    let blockers = app.blockers(task);
+    let style = if task.is_closed() {
+        Style::default().fg(theme.muted).add_modifier(Modifier::CROSSED_OUT)
+    } else if !blockers.is_empty() {
+        Style::default().fg(theme.muted)
+    } else {
+        Style::default().fg(theme.text)
+    };
+    let symbol = format!(" {} ", app.status_symbol(&task.status));
+    let symbol_color = match task.status {
+        Status::Doing => theme.warning,
+        Status::Waiting => theme.danger,
+        _ => theme.accent,
+    };
+    let priority_style = Style::default().fg(match task.priority {
+        Priority::High => theme.danger,
+        Priority::Medium => theme.warning,
+        Priority::Low => theme.success,
+    });
+    let priority_symbol = match task.priority {
+        Priority::High => " ▲",
+        Priority::Medium => " ●",
+        Priority::Low => " ▼",
+    };
+
+    let mut spans = Vec::new();
+    if depth > 0 {
+        spans.push(Span::raw(format!("{}↳ ", "  ".repeat(depth))));
+    }
+    spans.extend([
+        Span::styled(symbol, Style::default().fg(symbol_color)),
+        Span::raw(task.description.clone()),
+        Span::styled(priority_symbol, priority_style),
+    ]);
+
+    if let Some(due_date) = &task.due_date {
+        let due_date_style = if task.is_overdue(today) {
+            Style::default().fg(theme.danger)
+        } else {
+            Style::default().fg(theme.subtext)
+        };
+        spans.push(Span::styled(format!(" (due: {})", due_date), due_date_style));
+    }
+
+    if !blockers.is_empty() {
+        let ids: Vec<String> = blockers.iter().map(|blocker| format!("#{}", blocker.id)).collect();
+        spans.push(Span::styled(
+            format!(" ⊘ blocked by {}", ids.join(", ")),
+            Style::default().fg(theme.danger),
+        ));
+    }
+
+    if let Some(reason) = &task.waiting_on {
+        spans.push(Span::styled(format!(" (waiting on {})", reason), Style::default().fg(theme.subtext)));
+    }
+
+    if task.is_timing() {
+        spans.push(Span::styled(
+            format!(" ⏱ {}", format_duration(task.time_spent())),
+            Style::default().fg(theme.warning).add_modifier(Modifier::BOLD),
+        ));
+    } else if !task.time_entries.is_empty() {
+        spans.push(Span::styled(
+            format!(" ⏱ {}", format_duration(task.time_spent())),
+            Style::default().fg(theme.subtext),
+        ));
+    }
+
+    if !task.tags.is_empty() {
+        spans.push(Span::raw(" "));
+        for tag in task.tags.iter() {
+            spans.push(Span::styled(tag.clone(), Style::default().fg(theme.accent)));
             spans.push(Span::raw(" "));
-            for tag in task.tags.iter() {
-                spans.push(Span::styled(tag, Style::default().fg(MAUVE)));
-                spans.push(Span::raw(" "));
-            }
         }
+    }
 
-        items.push(ListItem::new(Line::from(spans)).style(style));
+    if let Some((name, color)) = project {
+        spans.push(Span::styled(format!(" @{}", name), Style::default().fg(color)));
+    }
 
-        for sub_task in task.sub_tasks.iter() {
-            let (style, symbol) = if sub_task.completed {
-                (Style::default().fg(SURFACE2).add_modifier(Modifier::CROSSED_OUT), " ✔ ")
-            } else {
-                (Style::default().fg(TEXT), " ❯ ")
-            };
-            let priority_style = Style::default().fg(match sub_task.priority {
-                Priority::High => RED,
-                Priority::Medium => YELLOW,
-                Priority::Low => GREEN,
-            });
-            let priority_symbol = match sub_task.priority {
-                Priority::High => " ▲",
-                Priority::Medium => " ●",
-                Priority::Low => " ▼",
+    if !task.notes.is_empty() {
+        spans.push(Span::styled(" ✎", Style::default().fg(theme.subtext)));
+    }
+
+    if archived {
+        spans.push(Span::styled(" [archived]", Style::default().fg(theme.muted)));
+    }
+
+    ListItem::new(Line::from(spans)).style(style)
+}
+
+fn render_details(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let label_style = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+    let value_style = Style::default().fg(theme.subtext);
+    let today = Local::now().date_naive();
+    let timestamp = |label: &'static str, value: Option<DateTime<Local>>| {
+        Line::from(vec![
+            Span::styled(label, value_style),
+            Span::styled(
+                value.map_or("—".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string()),
+                Style::default().fg(theme.text),
+            ),
+        ])
+    };
+
+    let mut lines = Vec::new();
+    match app.selected_task() {
+        Some(task) => {
+            lines.push(Line::styled(task.description.clone(), Style::default().fg(theme.text)));
+            lines.push(Line::raw(""));
+
+            let priority = match task.priority {
+                Priority::High => Span::styled("▲ high", Style::default().fg(theme.danger)),
+                Priority::Medium => Span::styled("● medium", Style::default().fg(theme.warning)),
+                Priority::Low => Span::styled("▼ low", Style::default().fg(theme.success)),
             };
+            lines.push(Line::from(vec![Span::styled("Priority  ", label_style), priority]));
 
-            let mut spans = vec![
-                Span::raw("  ↳ "),
-                Span::styled(symbol, Style::default().fg(MAUVE)),
-                Span::raw(sub_task.description.clone()),
-                Span::styled(priority_symbol, priority_style),
-            ];
+            let status = match &task.waiting_on {
+                Some(reason) => format!("{} {} on {}", app.status_symbol(&task.status), task.status.label(), reason),
+                None => format!("{} {}", app.status_symbol(&task.status), task.status.label()),
+            };
+            lines.push(Line::from(vec![
+                Span::styled("Status    ", label_style),
+                Span::styled(status, Style::default().fg(theme.text)),
+            ]));
+            lines.push(Line::from(vec![
+                Span::styled("ID        ", label_style),
+                Span::styled(format!("#{}", task.id), Style::default().fg(theme.text)),
+            ]));
 
-            if let Some(due_date) = &sub_task.due_date {
-                let due_date_style = if Local::now().format("%Y-%m-%d").to_string() > *due_date {
-                    Style::default().fg(RED)
+            if let Some(due_date) = &task.due_date {
+                let relative = NaiveDate::parse_from_str(due_date, "%Y-%m-%d")
+                    .map(|date| format!(" ({})", relative_days(date, today)))
+                    .unwrap_or_default();
+                let due_style = if task.is_overdue(&today.format("%Y-%m-%d").to_string()) {
+                    Style::default().fg(theme.danger)
                 } else {
-                    Style::default().fg(SUBTEXT1)
+                    Style::default().fg(theme.text)
                 };
-                spans.push(Span::styled(format!(" (due: {})", due_date), due_date_style));
+                lines.push(Line::from(vec![
+                    Span::styled("Due       ", label_style),
+                    Span::styled(format!("{}{}", due_date, relative), due_style),
+                ]));
             }
 
-            if !sub_task.tags.is_empty() {
-                spans.push(Span::raw(" "));
-                for tag in sub_task.tags.iter() {
-                    spans.push(Span::styled(tag, Style::default().fg(MAUVE)));
-                    spans.push(Span::raw(" "));
+            // Every blocker is listed, closed ones struck through
+            let links = [
+                ("Waits for ", task.blocked_by.iter().filter_map(|&id| app.find_id(id)).collect()),
+                ("Blocks    ", app.dependents(task.id)),
+            ];
+            for (label, tasks) in links {
+                for (i, linked) in tasks.into_iter().enumerate() {
+                    let style = if linked.is_closed() {
+                        Style::default().fg(theme.muted).add_modifier(Modifier::CROSSED_OUT)
+                    } else {
+                        Style::default().fg(theme.text)
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(if i == 0 { label } else { "          " }, label_style),
+                        Span::styled(format!("#{} {}", linked.id, linked.description), style),
+                    ]));
                 }
             }
 
-            items.push(ListItem::new(Line::from(spans)).style(style));
+            if let Some(project) = app.selected_row().and_then(|row| app.row_project(&row)) {
+                lines.push(Line::from(vec![
+                    Span::styled("Project   ", label_style),
+                    Span::styled(project.name.clone(), Style::default().fg(project_color(theme, project))),
+                ]));
+            }
+
+            if !task.time_entries.is_empty() {
+                let sessions = task.time_entries.len();
+                let mut time = format!(
+                    "{} in {} session{}",
+                    format_duration(task.time_spent()),
+                    sessions,
+                    if sessions == 1 { "" } else { "s" }
+                );
+                if let Some(entry) = task.time_entries.last().filter(|_| task.is_timing()) {
+                    time.push_str(&format!(", running since {}", entry.start.format("%H:%M")));
+                }
+                let style = if task.is_timing() { theme.warning } else { theme.text };
+                lines.push(Line::from(vec![
+                    Span::styled("Time      ", label_style),
+                    Span::styled(time, Style::default().fg(style)),
+                ]));
+            }
+
+            if task.pomodoros > 0 {
+                lines.push(Line::from(vec![
+                    Span::styled("Pomodoros ", label_style),
+                    Span::styled(task.pomodoros.to_string(), Style::default().fg(theme.text)),
+                ]));
+            }
+
+            if !task.tags.is_empty() {
+                lines.push(Line::from(vec![
+                    Span::styled("Tags      ", label_style),
+                    Span::styled(task.tags.join(" "), Style::default().fg(theme.accent)),
+                ]));
+            }
+
+            if !task.sub_tasks.is_empty() {
+                let done = task.sub_tasks.iter().filter(|t| t.is_closed()).count();
+                let total = task.sub_tasks.len();
+                lines.push(Line::raw(""));
+                lines.push(Line::from(vec![
+                    Span::styled("Subtasks  ", label_style),
+                    Span::styled(
+                        format!("{} {}/{}", progress_bar(done, total, 10), done, total),
+                        Style::default().fg(theme.success),
+                    ),
+                ]));
+                for sub_task in task.sub_tasks.iter() {
+                    let (check, style) = if sub_task.is_closed() {
+                        ("[x] ", Style::default().fg(theme.muted).add_modifier(Modifier::CROSSED_OUT))
+                    } else {
+                        ("[ ] ", Style::default().fg(theme.text))
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(check, Style::default().fg(theme.accent)),
+                        Span::styled(sub_task.description.clone(), style),
+                    ]));
+                }
+            }
+
+            if !task.notes.is_empty() {
+                lines.push(Line::raw(""));
+                lines.push(Line::styled("Notes", label_style));
+                lines.extend(task.notes.lines().map(|line| note_line(theme, line)));
+            }
+
+            lines.push(Line::raw(""));
+            lines.push(Line::styled("History", label_style));
+            lines.push(timestamp("Created   ", task.created_at));
+            lines.push(timestamp("Modified  ", task.modified_at));
+            lines.push(timestamp("Completed ", task.completed_at));
         }
+        None => lines.push(Line::styled("No task selected", value_style)),
     }
 
-    let title = match app.mode {
-        AppMode::Search if !app.search_input.is_empty() => {
-            format!(" To-Do (Search: {}) ", app.search_input)
-        }
-        AppMode::Search => " To-Do (Search Mode) ".to_string(),
-        _ => " To-Do ".to_string(),
-    };
+    let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border))
+            .title(" Details ")
+            .title_style(Style::default().fg(theme.title)),
+    );
 
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(title)
-                .border_style(Style::default().fg(SURFACE1))
-                .title_style(Style::default().fg(LAVENDER)),
+    f.render_widget(details, area);
+}
+
+/// Renders one line of notes with light markdown styling: headings,
+/// bullets and quotes.
+fn note_line(theme: &Theme, line: &str) -> Line<'static> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    if trimmed.starts_with('#') {
+        let heading = trimmed.trim_start_matches('#').trim_start();
+        Line::styled(
+            heading.to_string(),
+            Style::default().fg(theme.title).add_modifier(Modifier::BOLD),
         )
-        .highlight_style(
-            Style::default()
-                .bg(SURFACE0)
-                .fg(LAVENDER)
-                .add_modifier(Modifier::BOLD),
+    } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+        Line::from(vec![
+            Span::styled(format!("{}• ", indent), Style::default().fg(theme.accent)),
+            Span::styled(item.to_string(), Style::default().fg(theme.text)),
+        ])
+    } else if let Some(quote) = trimmed.strip_prefix('>') {
+        Line::styled(
+            format!("{}│{}", indent, quote),
+            Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC),
         )
-        .highlight_symbol(" ➤ ");
+    } else {
+        Line::styled(line.to_string(), Style::default().fg(theme.text))
+    }
+}
 
-    f.render_stateful_widget(list, area, &mut app.state);
+/// Describes `date` relative to `today`, e.g. "in 3 days" or "yesterday".
+fn relative_days(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        n if n > 1 => format!("in {} days", n),
+        n => format!("{} days ago", -n),
+    }
 }
 
-fn render_footer(f: &mut Frame, area: Rect) {
-    let key_style = Style::default().fg(MAUVE).add_modifier(Modifier::BOLD);
-    let desc_style = Style::default().fg(SUBTEXT1);
+fn progress_bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or(0);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
 
-    macro_rules! key {
-        ($key:expr, $desc:expr) => {
-            vec![Span::styled($key, key_style), Span::styled($desc, desc_style)]
-        };
+/// The actions the footer offers in the current mode, each with the
+/// label it is shown under.
+fn footer_actions(app: &App) -> &'static [(Action, &'static str)] {
+    match app.mode {
+        AppMode::Normal if app.view == View::Archive => &[
+            (Action::Quit, "quit"),
+            (Action::Restore, "restore"),
+            (Action::Delete, "delete"),
+            (Action::Purge, "purge"),
+            (Action::Search, "search"),
+            (Action::SwitchView, "tasks"),
+            (Action::Help, "help"),
+        ],
+        AppMode::Normal if app.view == View::Agenda => &[
+            (Action::Quit, "quit"),
+            (Action::ToggleDone, "done"),
+            (Action::DueDate, "due"),
+            (Action::PreviousDay, "previous-day"),
+            (Action::NextDay, "next-day"),
+            (Action::Today, "today"),
+            (Action::Cancel, "all-days"),
+            (Action::Search, "search"),
+            (Action::Help, "help"),
+        ],
+        AppMode::Normal if app.view == View::Stats => &[
+            (Action::Quit, "quit"),
+            (Action::NextProject, "next-project"),
+            (Action::PreviousProject, "previous-project"),
+            (Action::SwitchView, "archive"),
+            (Action::Help, "help"),
+        ],
+        AppMode::Normal if app.view == View::Board => &[
+            (Action::Quit, "quit"),
+            (Action::Add, "add"),
+            (Action::ToggleDone, "done"),
+            (Action::MoveLeft, "move-left"),
+            (Action::MoveRight, "move-right"),
+            (Action::Group, "group"),
+            (Action::Search, "search"),
+            (Action::Help, "help"),
+        ],
+        AppMode::Normal => &[
+            (Action::Quit, "quit"),
+            (Action::Add, "add"),
+            (Action::AddSubtask, "subtask"),
+            (Action::ToggleDone, "done"),
+            (Action::Delete, "delete"),
+            (Action::Priority, "priority"),
+            (Action::DueDate, "due"),
+            (Action::Search, "search"),
+            (Action::Help, "help"),
+        ],
+        AppMode::Select => &[
+            (Action::Mark, "mark"),
+            (Action::MarkAll, "mark-all"),
+            (Action::ToggleDone, "done"),
+            (Action::Priority, "priority"),
+            (Action::DueDate, "due"),
+            (Action::Tags, "tags"),
+            (Action::Delete, "delete"),
+            (Action::Undo, "undo"),
+            (Action::Cancel, "exit"),
+            (Action::Help, "help"),
+        ],
+        AppMode::Insert => &[(Action::Submit, "add"), (Action::Cancel, "cancel")],
+        AppMode::DateInput => &[(Action::Submit, "set-due"), (Action::Cancel, "cancel")],
+        AppMode::TagInput => &[(Action::Submit, "apply"), (Action::Cancel, "cancel")],
+        AppMode::Search => &[
+            (Action::Submit, "keep-filter"),
+            (Action::Cancel, "clear"),
+            (Action::SearchArchive, "archive"),
+        ],
+        AppMode::Notes => &[
+            (Action::Submit, "save"),
+            (Action::NewLine, "newline"),
+            (Action::Cancel, "cancel"),
+        ],
+        AppMode::Confirm(_) => &[(Action::Submit, "confirm"), (Action::Cancel, "cancel")],
+        AppMode::Command => &[
+            (Action::Submit, "run"),
+            (Action::Complete, "complete"),
+            (Action::Cancel, "cancel"),
+        ],
+        AppMode::Picker(_) => &[
+            (Action::Submit, "run"),
+            (Action::Down, "next"),
+            (Action::Up, "previous"),
+            (Action::Cancel, "cancel"),
+        ],
+        AppMode::Help(_) => &[
+            (Action::Down, "scroll-down"),
+            (Action::Up, "scroll-up"),
+            (Action::Cancel, "close"),
+        ],
     }
+}
 
-    let help_spans = Line::from(
-        key!("q", ":quit ")
-            .into_iter()
-            .chain(key!("a", ":add "))
-            .chain(key!("d", ":delete "))
-            .chain(key!("/", ":search "))
-            .chain(key!("+", ":zoom-in "))
-            .chain(key!("-", ":zoom-out"))
-            .collect::<Vec<_>>(),
-    );
+/// The footer's key hints, each with the first key bound to its action
+/// in the active keymap. Unbound actions are left out.
+fn footer_keys(app: &App) -> Vec<(String, &'static str, Action)> {
+    let context = app.key_context();
+    footer_actions(app)
+        .iter()
+        .filter_map(|&(action, label)| {
+            let key = app.keymap.keys_for(context, action).into_iter().next()?;
+            Some((key, label, action))
+        })
+        .collect()
+}
 
-    let help = Paragraph::new(help_spans).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(SURFACE1))
-            .title(" Controls ")
-            .title_style(Style::default().fg(LAVENDER)),
-    );
+fn render_footer(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.theme;
+    let key_style = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+    let desc_style = Style::default().fg(theme.subtext);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+        .title(" Controls ")
+        .title_style(Style::default().fg(theme.title));
+    // The pomodoro's countdown sits on the right of the border
+    let block = match &app.pomodoro {
+        Some(pomodoro) => {
+            let color = if pomodoro.phase == Phase::Work { theme.danger } else { theme.success };
+            let task = app.find_id(pomodoro.task_id).map_or(String::new(), |task| task.description.clone());
+            let countdown = Line::from(vec![
+                Span::styled(
+                    format!(" ● {} {} ", pomodoro.phase.label(), format_remaining(pomodoro.remaining())),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!("{} ", task), Style::default().fg(theme.subtext)),
+            ]);
+            block.title(Title::from(countdown).alignment(Alignment::Right))
+        }
+        None => block,
+    };
+    let inner = block.inner(area);
+
+    let mut key_areas = Vec::new();
+    let help_spans = match &app.status_message {
+        Some(message) => Line::styled(message.clone(), Style::default().fg(theme.text)),
+        None => {
+            let mut spans = Vec::new();
+            let mut x = inner.x;
+            for (key, label, action) in footer_keys(app) {
+                let desc = format!(":{} ", label);
+                let width = (key.chars().count() + desc.len()) as u16;
+                let hit = Rect::new(x, inner.y, width, 1).intersection(inner);
+                key_areas.push((hit, action));
+                x = x.saturating_add(width);
+                spans.push(Span::styled(key, key_style));
+                spans.push(Span::styled(desc, desc_style));
+            }
+            Line::from(spans)
+        }
+    };
+
+    let help = Paragraph::new(help_spans).block(block);
 
     f.render_widget(help, area);
+    app.areas.footer_keys = key_areas;
 }
 
-fn render_input_popup(f: &mut Frame, app: &App) {
+fn render_input_popup(f: &mut Frame, app: &App) -> Rect {
+    let theme = &app.theme;
     let area = centered_rect(60, 20, f.size());
     let input_block = Paragraph::new(app.input.as_str())
         .block(
             Block::default()
                 .title(" New Task ")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(MAUVE))
-                .title_style(Style::default().fg(LAVENDER)),
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
         )
-        .style(Style::default().fg(TEXT));
+        .style(Style::default().fg(theme.text));
 
     f.render_widget(Clear, area); //this clears the background
     f.render_widget(input_block, area);
+    area
 }
 
-fn render_date_input_popup(f: &mut Frame, app: &App) {
+fn render_date_input_popup(f: &mut Frame, app: &App) -> Rect {
+    let theme = &app.theme;
     let area = centered_rect(60, 20, f.size());
     let input_block = Paragraph::new(app.date_input.as_str())
         .block(
             Block::default()
                 .title(" Set Due Date ")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(MAUVE))
-                .title_style(Style::default().fg(LAVENDER)),
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
+        )
+        .style(Style::default().fg(theme.text));
+
+    f.render_widget(Clear, area);
+    f.render_widget(input_block, area);
+    area
+}
+
+fn render_tag_input_popup(f: &mut Frame, app: &App) -> Rect {
+    let theme = &app.theme;
+    let area = centered_rect(60, 20, f.size());
+    let input_text = format!("{}\n\nAdd with +tag or tag, remove with -tag", app.tag_input);
+    let input_block = Paragraph::new(input_text)
+        .block(
+            Block::default()
+                .title(" Edit Tags ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
+        )
+        .style(Style::default().fg(theme.text));
+
+    f.render_widget(Clear, area);
+    f.render_widget(input_block, area);
+    area
+}
+
+fn render_confirm_popup(f: &mut Frame, app: &App, action: PendingAction) -> Rect {
+    let theme = &app.theme;
+    let area = centered_rect(50, 20, f.size());
+    let key_style = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+    let desc_style = Style::default().fg(theme.subtext);
+    let text = vec![
+        Line::styled(app.confirm_summary(action), Style::default().fg(theme.text)),
+        Line::raw(""),
+        Line::from(vec![
+            Span::styled(first_key(app, KeyContext::Confirm, Action::Submit), key_style),
+            Span::styled(":confirm ", desc_style),
+            Span::styled(first_key(app, KeyContext::Confirm, Action::Cancel), key_style),
+            Span::styled(":cancel", desc_style),
+        ]),
+    ];
+    let confirm_block = Paragraph::new(text)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(" Confirm ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.danger))
+                .title_style(Style::default().fg(theme.title)),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(confirm_block, area);
+    area
+}
+
+fn render_notes_popup(f: &mut Frame, app: &App) -> Rect {
+    let theme = &app.theme;
+    let area = centered_rect(70, 60, f.size());
+    let input_block = Paragraph::new(app.notes_input.as_str())
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(format!(
+                    " Notes ({}: save, {}: cancel) ",
+                    first_key(app, KeyContext::Notes, Action::Submit),
+                    first_key(app, KeyContext::Notes, Action::Cancel)
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
         )
-        .style(Style::default().fg(TEXT));
+        .style(Style::default().fg(theme.text));
 
     f.render_widget(Clear, area);
     f.render_widget(input_block, area);
+    area
 }
 
-fn render_search_popup(f: &mut Frame, app: &App) {
+fn render_search_popup(f: &mut Frame, app: &App) -> Rect {
+    let theme = &app.theme;
     let area = centered_rect(80, 20, f.size());
-    let search_help = "Search by: description, tags, priority (high/medium/low), status (completed/incomplete), due date";
-    let input_text = format!("{}\n\n{}", app.search_input, search_help);
+    let search_help = "Search by: description, notes, tags, priority (high/medium/low), status (todo/doing/waiting/done/cancelled/open/closed), due date";
+    let archive_help = match app.view {
+        View::Tasks if app.search_archive => "Tab: exclude archived tasks",
+        View::Tasks => "Tab: include archived tasks",
+        View::Archive | View::Board | View::Next | View::Agenda | View::Stats => "",
+    };
+    let input_text = format!("{}\n\n{}\n{}", app.search_input, search_help, archive_help);
     
     let input_block = Paragraph::new(input_text)
         .block(
             Block::default()
                 .title(" Search Tasks ")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(MAUVE))
-                .title_style(Style::default().fg(LAVENDER)),
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
         )
-        .style(Style::default().fg(TEXT));
+        .style(Style::default().fg(theme.text));
+
+    f.render_widget(Clear, area);
+    f.render_widget(input_block, area);
+    area
+}
+
+fn render_command_popup(f: &mut Frame, app: &App) -> Rect {
+    let theme = &app.theme;
+    let area = centered_rect(60, 20, f.size());
+    let text = vec![
+        Line::styled(format!(":{}", app.command_input), Style::default().fg(theme.text)),
+        Line::raw(""),
+        Line::styled(
+            "e.g. sort due, tag +work, due friday, archive done, export md tasks.md, theme latte",
+            Style::default().fg(theme.muted),
+        ),
+    ];
+    let input_block = Paragraph::new(text)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(" Command ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
+        );
 
     f.render_widget(Clear, area);
     f.render_widget(input_block, area);
+    area
+}
+
+/// Actions and saved views matching the query, each action with the key
+/// it has in the mode the picker was opened from.
+fn render_picker_popup(f: &mut Frame, app: &mut App, context: KeyContext) -> Rect {
+    let area = centered_rect(60, 60, f.size());
+    let items = picker_items(app, &app.picker_input);
+    app.picker_selected = app.picker_selected.min(items.len().saturating_sub(1));
+
+    let theme = &app.theme;
+    let block = Block::default()
+        .title(" Actions ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title_style(Style::default().fg(theme.title));
+    let inner = block.inner(area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+
+    let query = Paragraph::new(Line::from(vec![
+        Span::styled("> ", Style::default().fg(theme.accent)),
+        Span::styled(app.picker_input.as_str(), Style::default().fg(theme.text)),
+    ]));
+
+    let width = inner.width as usize;
+    let rows: Vec<ListItem> = items
+        .iter()
+        .map(|item| {
+            let key = match item {
+                PickerItem::Action(action) => {
+                    app.keymap.keys_for(context, *action).into_iter().next().unwrap_or_default()
+                }
+                PickerItem::View(_) => String::new(),
+            };
+            let label = item.label();
+            let gap = width.saturating_sub(label.chars().count() + key.chars().count() + 1);
+            ListItem::new(Line::from(vec![
+                Span::styled(label, Style::default().fg(theme.text)),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(key, Style::default().fg(theme.muted)),
+            ]))
+        })
+        .collect();
+    let list = List::new(rows).highlight_style(
+        Style::default()
+            .bg(theme.selection)
+            .add_modifier(Modifier::BOLD),
+    );
+    let mut state = ListState::default().with_selected(Some(app.picker_selected));
+
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    f.render_widget(query, chunks[0]);
+    f.render_stateful_widget(list, chunks[1], &mut state);
+    area
+}
+
+/// The first key bound to `action`, or `-` if it has none.
+fn first_key(app: &App, context: KeyContext, action: Action) -> String {
+    app.keymap
+        .keys_for(context, action)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Every binding of the active keymap, grouped by mode, starting with the
+/// mode help was opened from.
+fn render_help_popup(f: &mut Frame, app: &mut App, current: KeyContext) -> Rect {
+    let theme = &app.theme;
+    let area = centered_rect(60, 80, f.size());
+    let heading_style = Style::default().fg(theme.title).add_modifier(Modifier::BOLD);
+    let key_style = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+    let desc_style = Style::default().fg(theme.text);
+    let muted_style = Style::default().fg(theme.muted);
+
+    let contexts = std::iter::once(current)
+        .chain(KeyContext::ALL.into_iter().filter(|&c| c != current));
+    let mut lines: Vec<Line> = Vec::new();
+    for context in contexts {
+        let actions = app.keymap.actions(context);
+        if actions.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Line::raw(""));
+        }
+        lines.push(Line::styled(context.title(), heading_style));
+
+        let keys: Vec<String> = actions.iter().map(|(_, keys)| keys.join(", ")).collect();
+        let width = keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+        for ((action, _), keys) in actions.iter().zip(&keys) {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<width$}  ", keys, width = width), key_style),
+                Span::styled(action.description(), desc_style),
+            ]));
+        }
+        if context.takes_text() {
+            lines.push(Line::styled("  Other keys type text; backspace deletes", muted_style));
+        }
+    }
+
+    // Stop scrolling once the last line is in view
+    let height = area.height.saturating_sub(2);
+    let max_scroll = (lines.len() as u16).saturating_sub(height);
+    app.help_scroll = app.help_scroll.min(max_scroll);
+
+    let theme = &app.theme;
+    let help = Paragraph::new(lines)
+        .scroll((app.help_scroll, 0))
+        .block(
+            Block::default()
+                .title(" Keys ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent))
+                .title_style(Style::default().fg(theme.title)),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+    area
 }
 
 /// helper function to create a centered rect using up certain percentage of the available rect `r`